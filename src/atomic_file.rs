@@ -0,0 +1,39 @@
+use std::{ffi::OsString, fs, io, path::{Path, PathBuf}};
+
+// Writes to a temp file next to `path` and renames it into place, so a crash or a concurrent
+// reader never sees half a file
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp_path = sibling(path, "tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(tmp_path, path)
+}
+
+// teams.csv -> teams.csv.lock, keeping the original extension so config.toml and config.json
+// don't end up sharing config.tmp
+pub fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_replaces_the_file_and_cleans_up() {
+        let path = env::temp_dir().join(format!("footy-atomic-{}.toml", std::process::id()));
+        fs::write(&path, "old").unwrap();
+
+        write_atomic(&path, b"new").unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(contents, "new");
+        assert!(!sibling(&path, "tmp").exists());
+        assert_eq!(sibling(Path::new("dir/config.toml"), "tmp"), Path::new("dir/config.toml.tmp"));
+    }
+}
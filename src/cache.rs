@@ -0,0 +1,247 @@
+use std::{env, fs, io, path::PathBuf};
+
+use chrono::Utc;
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{atomic_file::write_atomic, status::FixtureStatus};
+
+// TTLs in seconds, picked to keep the free ~100 calls/day plan usable
+const LIVE_TTL: i64 = 15;
+const IN_PROGRESS_TTL: i64 = 30;
+const UPCOMING_TTL: i64 = 15 * 60;
+const STANDINGS_TTL: i64 = 6 * 60 * 60;
+const FINISHED_TTL: i64 = 7 * 24 * 60 * 60;
+const REFERENCE_TTL: i64 = 7 * 24 * 60 * 60;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CacheMode {
+    // serve fresh entries, fetch and store otherwise
+    #[default]
+    Normal,
+    // always fetch, but still store the result
+    Refresh,
+    // never touch the network, serve entries of any age
+    Offline,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    url: String,
+    fetched_at: i64,
+    ttl: i64,
+    body: String,
+}
+
+// FOOTY_CACHE_DIR wins, then $XDG_CACHE_HOME/footy (or the platform equivalent)
+pub fn cache_dir() -> Option<PathBuf> {
+    match env::var("FOOTY_CACHE_DIR") {
+        Ok(dir) => Some(PathBuf::from(dir)),
+        Err(_) => Some(dirs::cache_dir()?.join("footy")),
+    }
+}
+
+// Response bodies keyed by request URL, one JSON file per URL
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf) -> ResponseCache {
+        ResponseCache { dir }
+    }
+
+    pub fn default_location() -> Option<ResponseCache> {
+        Some(ResponseCache::new(cache_dir()?))
+    }
+
+    pub fn get_fresh(&self, url: &str) -> Option<String> {
+        let entry = self.read(url)?;
+        if Utc::now().timestamp() - entry.fetched_at < entry.ttl {
+            Some(entry.body)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_any(&self, url: &str) -> Option<String> {
+        self.read(url).map(|entry| entry.body)
+    }
+
+    pub fn put(&self, url: &str, body: &str) -> io::Result<()> {
+        if !is_cacheable(body) { return Ok(()); }
+
+        let entry = CacheEntry {
+            url: url.to_string(),
+            fetched_at: Utc::now().timestamp(),
+            ttl: ttl_for(url, body),
+            body: body.to_string(),
+        };
+
+        fs::create_dir_all(&self.dir)?;
+        write_atomic(&self.path_for(url), &serde_json::to_vec(&entry)?)
+    }
+
+    fn read(&self, url: &str) -> Option<CacheEntry> {
+        let contents = fs::read(self.path_for(url)).ok()?;
+        let entry: CacheEntry = serde_json::from_slice(&contents).ok()?;
+        // guard against the (unlikely) hash collision
+        if entry.url == url { Some(entry) } else { None }
+    }
+
+    fn path_for(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", fnv1a_hash(url)))
+    }
+}
+
+// Stable across Rust versions, unlike DefaultHasher, so cache files survive upgrades
+fn fnv1a_hash(input: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in input.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// Error envelopes come back as HTTP 200 too, and must never be cached
+fn is_cacheable(body: &str) -> bool {
+    let data: Value = match serde_json::from_str(body) {
+        Ok(data) => data,
+        Err(_) => return false,
+    };
+
+    let no_errors = match data.get("errors") {
+        None | Some(Value::Null) => true,
+        Some(Value::Array(errors)) => errors.is_empty(),
+        Some(Value::Object(errors)) => errors.is_empty(),
+        Some(_) => false,
+    };
+
+    no_errors && data.get("response").is_some()
+}
+
+fn ttl_for(url: &str, body: &str) -> i64 {
+    if url.contains("live=") {
+        LIVE_TTL
+    } else if url.contains("/standings?") {
+        STANDINGS_TTL
+    } else if url.contains("/fixtures?") && is_rolling(url) {
+        // "a team's last 2" gains a new result as soon as its next match ends
+        UPCOMING_TTL
+    } else if url.contains("/fixtures?") {
+        fixtures_ttl(body)
+    } else {
+        REFERENCE_TTL
+    }
+}
+
+// last=/next= queries are relative to now, unlike ones for a fixed date or date range
+fn is_rolling(url: &str) -> bool {
+    let query = url.split_once('?').map_or("", |(_, query)| query);
+    query.split('&').any(|pair| pair.starts_with("last=") || pair.starts_with("next="))
+}
+
+// Finished results don't change, anything still to be played or in progress does
+fn fixtures_ttl(body: &str) -> i64 {
+    let data: Value = serde_json::from_str(body).unwrap_or_default();
+    let statuses: Vec<FixtureStatus> = data["response"].as_array()
+        .map(|fixtures| fixtures.iter()
+            .map(|fixture| FixtureStatus::from_code(fixture["fixture"]["status"]["short"].as_str().unwrap_or("")))
+            .collect())
+        .unwrap_or_default();
+
+    // cancelled and abandoned fixtures won't change any more; postponed ones will get a new date
+    let settled = |status: &FixtureStatus| {
+        status.is_finished() || (status.is_called_off() && *status != FixtureStatus::Postponed)
+    };
+
+    if statuses.is_empty() {
+        UPCOMING_TTL
+    } else if statuses.iter().any(FixtureStatus::is_live) {
+        IN_PROGRESS_TTL
+    } else if statuses.iter().all(settled) {
+        FINISHED_TTL
+    } else if statuses.iter().all(|status| status.is_finished() || status.is_called_off() || status.is_not_started()) {
+        UPCOMING_TTL
+    } else {
+        // codes API-Football added later; assume they're still changing
+        IN_PROGRESS_TTL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures_body(statuses: &[&str]) -> String {
+        let fixtures: Vec<String> = statuses.iter()
+            .map(|status| format!(r#"{{"fixture": {{"status": {{"short": "{}"}}}}}}"#, status))
+            .collect();
+        format!(r#"{{"errors": [], "response": [{}]}}"#, fixtures.join(","))
+    }
+
+    fn test_cache(name: &str) -> ResponseCache {
+        let dir = env::temp_dir().join(format!("footy-cache-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        ResponseCache::new(dir)
+    }
+
+    #[test]
+    fn test_ttl_per_endpoint() {
+        let body = fixtures_body(&["FT", "PEN"]);
+
+        assert_eq!(ttl_for("https://host/v3/fixtures?live=39-140", &body), LIVE_TTL);
+        assert_eq!(ttl_for("https://host/v3/standings?league=39&season=2024", &body), STANDINGS_TTL);
+        assert_eq!(ttl_for("https://host/v3/fixtures?season=2024&team=40&last=2", &body), UPCOMING_TTL);
+        assert_eq!(ttl_for("https://host/v3/fixtures?team=40&next=1", &fixtures_body(&["NS"])), UPCOMING_TTL);
+        assert_eq!(ttl_for("https://host/v3/fixtures?league=39&season=2024&from=2024-08-01&to=2024-08-14", &body), FINISHED_TTL);
+        assert_eq!(ttl_for("https://host/v3/fixtures?league=39", &fixtures_body(&["FT", "NS"])), UPCOMING_TTL);
+        assert_eq!(ttl_for("https://host/v3/fixtures?league=39", &fixtures_body(&["FT", "2H"])), IN_PROGRESS_TTL);
+        assert_eq!(ttl_for("https://host/v3/fixtures?league=39", &fixtures_body(&["FT", "CANC"])), FINISHED_TTL);
+        assert_eq!(ttl_for("https://host/v3/fixtures?league=39", &fixtures_body(&["PST", "NS"])), UPCOMING_TTL);
+        assert_eq!(ttl_for("https://host/v3/fixtures?league=39", &fixtures_body(&["CANC", "HT"])), IN_PROGRESS_TTL);
+        assert_eq!(ttl_for("https://host/v3/fixtures?league=39", &fixtures_body(&["FT", "XYZ"])), IN_PROGRESS_TTL);
+    }
+
+    #[test]
+    fn test_put_and_get_round_trip() {
+        let cache = test_cache("round-trip");
+        let url = "https://host/v3/standings?league=39&season=2024";
+        let body = r#"{"errors": [], "response": []}"#;
+
+        cache.put(url, body).unwrap();
+
+        assert_eq!(cache.get_fresh(url).as_deref(), Some(body));
+        assert_eq!(cache.get_any("https://host/v3/standings?league=140&season=2024"), None);
+    }
+
+    #[test]
+    fn test_error_envelopes_are_not_cached() {
+        let cache = test_cache("errors");
+        let url = "https://host/v3/standings?league=39&season=2024";
+
+        cache.put(url, r#"{"errors": {"token": "Error/Missing application key."}, "response": []}"#).unwrap();
+
+        assert_eq!(cache.get_any(url), None);
+    }
+
+    #[test]
+    fn test_stale_entries_only_served_by_get_any() {
+        let cache = test_cache("stale");
+        let url = "https://host/v3/fixtures?live=39";
+        let entry = CacheEntry {
+            url: url.to_string(),
+            fetched_at: Utc::now().timestamp() - 60,
+            ttl: LIVE_TTL,
+            body: fixtures_body(&["1H"]),
+        };
+        fs::create_dir_all(&cache.dir).unwrap();
+        fs::write(cache.path_for(url), serde_json::to_vec(&entry).unwrap()).unwrap();
+
+        assert_eq!(cache.get_fresh(url), None);
+        assert_eq!(cache.get_any(url), Some(fixtures_body(&["1H"])));
+    }
+}
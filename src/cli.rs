@@ -0,0 +1,259 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::{color::ColorChoice, layout::Layout};
+
+#[derive(Parser, Debug)]
+#[command(name = "footy", version, about = "Quick access to global football scores and schedules")]
+pub struct Cli {
+    /// Output format for fixtures and tables [default: table, or `format` in config.toml]
+    #[arg(long, global = true, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// How much detail rows show [default: auto, picked from the terminal width, or `layout` in config.toml]
+    #[arg(long, global = true, value_enum)]
+    pub layout: Option<Layout>,
+
+    /// When to colour output; auto colours terminals only and honours NO_COLOR
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Path to config.toml (defaults to ~/.config/footy/config.toml)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Ignore cached responses and fetch everything again
+    #[arg(long, global = true, conflicts_with = "offline")]
+    pub refresh: bool,
+
+    /// Only use cached responses, never call the API
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// Show the latest results for your followed teams
+    Scores(ScoresArgs),
+    /// Show the day's fixtures for your preferred leagues
+    Schedule(ScheduleArgs),
+    /// Add, remove, list or import your followed teams (interactive without a subcommand)
+    Teams(TeamsArgs),
+    /// Show live scores across your full list of leagues
+    Live(LiveArgs),
+    /// Show league tables for your preferred leagues
+    Standings(StandingsArgs),
+    /// Add, remove, reorder or list your followed leagues
+    Leagues(LeaguesArgs),
+    /// Manage the team colours file
+    Colors(ColorsArgs),
+    /// Show remaining API requests and today's usage
+    Quota,
+}
+
+#[derive(Args, Debug)]
+pub struct ScoresArgs {
+    /// Only show these followed teams (name or team ID), comma separated or repeated
+    #[arg(short, long = "team", value_delimiter = ',')]
+    pub teams: Vec<String>,
+
+    /// Number of past fixtures to show per team
+    #[arg(long, default_value_t = 2)]
+    pub last: u8,
+
+    /// Also show half-time scores
+    #[arg(long = "ht")]
+    pub half_time: bool,
+
+    /// Season to query, e.g. 2024 for 24/25
+    #[arg(short, long)]
+    pub season: Option<u16>,
+}
+
+#[derive(Args, Debug)]
+pub struct ScheduleArgs {
+    /// Day to show fixtures for (YYYY-MM-DD), defaults to today
+    #[arg(short, long)]
+    pub date: Option<NaiveDate>,
+
+    /// League IDs to show instead of your preferred leagues
+    #[arg(short, long = "league", value_delimiter = ',')]
+    pub leagues: Vec<u64>,
+
+    /// Season to query, e.g. 2024 for 24/25
+    #[arg(short, long)]
+    pub season: Option<u16>,
+}
+
+#[derive(Args, Debug)]
+pub struct LiveArgs {
+    /// League IDs to show instead of your full list of leagues
+    #[arg(short, long = "league", value_delimiter = ',')]
+    pub leagues: Vec<u64>,
+
+    /// Also show half-time scores
+    #[arg(long = "ht")]
+    pub half_time: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct StandingsArgs {
+    /// League IDs to show instead of your preferred leagues
+    #[arg(short, long = "league", value_delimiter = ',')]
+    pub leagues: Vec<u64>,
+
+    /// Season to query, e.g. 2024 for 24/25
+    #[arg(short, long)]
+    pub season: Option<u16>,
+}
+
+#[derive(Args, Debug)]
+pub struct TeamsArgs {
+    #[command(subcommand)]
+    pub action: Option<TeamsAction>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum TeamsAction {
+    /// Search for a team and follow it, asking which one when several match
+    Add(TeamAddArgs),
+    /// Stop following a team
+    Remove(TeamRemoveArgs),
+    /// Show the teams you follow
+    List(TeamListArgs),
+    /// Follow every team in a CSV file of name,id rows (the teams.csv format)
+    Import(TeamImportArgs),
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct TeamAddArgs {
+    /// Team name to search for, e.g. Arsenal
+    #[arg(required_unless_present = "id", conflicts_with = "id")]
+    pub name: Vec<String>,
+
+    /// Only consider teams from this country, e.g. England
+    #[arg(long)]
+    pub country: Option<String>,
+
+    /// Follow the team with this ID without searching
+    #[arg(long)]
+    pub id: Option<u64>,
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct TeamRemoveArgs {
+    /// Team name or ID
+    #[arg(required = true)]
+    pub team: Vec<String>,
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct TeamListArgs {
+    /// Print the list as JSON, same as --format json
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct TeamImportArgs {
+    /// CSV file to import
+    pub file: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct LeaguesArgs {
+    #[command(subcommand)]
+    pub action: Option<LeaguesAction>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum LeaguesAction {
+    /// Search for a league by name or country and follow it
+    Add(LeagueAddArgs),
+    /// Stop following a league
+    Remove(LeagueRemoveArgs),
+    /// Show your preferred and live leagues (the default)
+    List,
+    /// Move one of your preferred leagues to a new position
+    Move(LeagueMoveArgs),
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct LeagueAddArgs {
+    /// League name or country to search for, e.g. Eredivisie or Japan
+    #[arg(required_unless_present = "id", conflicts_with = "id")]
+    pub query: Vec<String>,
+
+    /// Follow the league with this ID without searching
+    #[arg(long)]
+    pub id: Option<u64>,
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct LeagueRemoveArgs {
+    /// League ID or name
+    pub league: String,
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct LeagueMoveArgs {
+    /// ID of the league to move
+    pub league: u64,
+
+    /// New position, 1 being the first league shown
+    #[arg(value_parser = clap::value_parser!(u16).range(1..))]
+    pub position: u16,
+}
+
+#[derive(Args, Debug)]
+pub struct ColorsArgs {
+    #[command(subcommand)]
+    pub action: ColorsAction,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum ColorsAction {
+    /// Convert an old id_rgb.csv into the id,name,primary,secondary format
+    Migrate(ColorMigrateArgs),
+    /// Work out team colours from logo images and save them to the colours file
+    Generate(ColorGenerateArgs),
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct ColorMigrateArgs {
+    /// Old colours file to read
+    #[arg(long, value_name = "PATH", default_value = "./id_rgb.csv")]
+    pub from: PathBuf,
+
+    /// Where to write the new file [default: `file` under [colors] in config.toml, or ./team_colors.csv]
+    #[arg(long, value_name = "PATH")]
+    pub to: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct ColorGenerateArgs {
+    /// Logo images named after the team ID, e.g. 40.png
+    #[arg(value_name = "LOGO", required_unless_present = "followed")]
+    pub logos: Vec<PathBuf>,
+
+    /// Download (or reuse cached) logos for every team in the teams file
+    #[arg(long)]
+    pub followed: bool,
+
+    /// Colours file to update [default: `file` under [colors] in config.toml, or ./team_colors.csv]
+    #[arg(long, value_name = "PATH")]
+    pub to: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+}
@@ -0,0 +1,537 @@
+use std::{future::Future, time::Duration};
+
+use reqwest::{Client, StatusCode};
+
+use crate::{
+    cache::{CacheMode, ResponseCache},
+    config::ApiSettings,
+    error::FootyError,
+    get_fixtures_url_by_date_range, get_fixtures_url_by_league, get_league_search_url, get_league_url, get_live_fixtures_url,
+    get_standings_url, get_team_by_id_url, get_team_leagues_url, get_team_search_url, get_team_url, quota::QuotaTracker, parse_fixtures, parse_leagues,
+    retry::{is_retryable_error, is_retryable_status, retry_after, RetryPolicy}, parse_standings, parse_teams, Fixture, LeagueInfo, TeamInfo,
+    TeamStanding,
+};
+
+const RAPIDAPI_HOST: &str = "api-football-v1.p.rapidapi.com";
+const API_SPORTS_HOST: &str = "v3.football.api-sports.io";
+
+// Where API-Football is hosted: RapidAPI's proxy or api-sports.io directly.
+// Keys from one don't work on the other, so the mode has to match the key.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Provider {
+    #[default]
+    RapidApi,
+    ApiSports,
+}
+
+impl Provider {
+    pub fn from_name(name: &str) -> Result<Provider, FootyError> {
+        match name.trim().to_lowercase().as_str() {
+            "rapidapi" => Ok(Provider::RapidApi),
+            "api-sports" | "apisports" | "api-sports.io" => Ok(Provider::ApiSports),
+            other => Err(FootyError::Config(format!("Unknown API provider '{}'. Use 'rapidapi' or 'api-sports'", other))),
+        }
+    }
+
+    pub fn host(&self) -> &'static str {
+        match self {
+            Provider::RapidApi => RAPIDAPI_HOST,
+            Provider::ApiSports => API_SPORTS_HOST,
+        }
+    }
+
+    // RapidAPI serves the API under /v3, api-sports.io from the root of its v3 host
+    pub fn base_url(&self) -> String {
+        match self {
+            Provider::RapidApi => format!("https://{}/v3", RAPIDAPI_HOST),
+            Provider::ApiSports => format!("https://{}", API_SPORTS_HOST),
+        }
+    }
+
+    fn auth_header(&self) -> &'static str {
+        match self {
+            Provider::RapidApi => "X-RapidAPI-Key",
+            Provider::ApiSports => "x-apisports-key",
+        }
+    }
+}
+
+// Failed attempt, and whether trying again could help
+enum FetchError {
+    Transient(FootyError, Option<Duration>),
+    Fatal(FootyError),
+}
+
+// Owns everything needed to talk to API-Football so fetchers only deal in typed data
+pub struct ApiFootballClient {
+    http: Client,
+    provider: Provider,
+    key: String,
+    base_url: String,
+    cache: Option<ResponseCache>,
+    cache_mode: CacheMode,
+    quota: Option<QuotaTracker>,
+    retry: RetryPolicy,
+}
+
+impl ApiFootballClient {
+    pub fn new(provider: Provider, key: String) -> ApiFootballClient {
+        ApiFootballClient {
+            http: build_http_client(RetryPolicy::default().timeout),
+            provider,
+            key,
+            base_url: provider.base_url(),
+            cache: None,
+            cache_mode: CacheMode::Normal,
+            quota: None,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> ApiFootballClient {
+        self.http = build_http_client(retry.timeout);
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> ApiFootballClient {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_cache(mut self, cache: Option<ResponseCache>) -> ApiFootballClient {
+        self.cache = cache;
+        self
+    }
+
+    pub fn with_cache_mode(mut self, cache_mode: CacheMode) -> ApiFootballClient {
+        self.cache_mode = cache_mode;
+        self
+    }
+
+    pub fn with_quota_tracker(mut self, quota: Option<QuotaTracker>) -> ApiFootballClient {
+        self.quota = quota;
+        self
+    }
+
+    pub fn from_settings(api: &ApiSettings) -> Result<ApiFootballClient, FootyError> {
+        let key = api.key.clone().ok_or_else(|| FootyError::Config(format!(
+            "{} is not set. Add it to your environment or set `key` under [api] in config.toml to call the API",
+            api.key_source,
+        )))?;
+
+        let client = ApiFootballClient::new(api.provider, key)
+            .with_retry_policy(api.retry)
+            .with_cache(ResponseCache::default_location())
+            .with_quota_tracker(QuotaTracker::default_location());
+
+        // base_url lets the CLI run against a local mock server
+        match &api.base_url {
+            Some(base_url) => Ok(client.with_base_url(base_url)),
+            None => Ok(client),
+        }
+    }
+
+    pub async fn fixtures_by_league(&self, league_id: u64, date: &str, season: u16) -> Result<Vec<Fixture>, FootyError> {
+        let body = self.get(get_fixtures_url_by_league(&self.base_url, league_id, date, season)).await?;
+        parse_fixtures(&body)
+    }
+
+    pub async fn live_fixtures(&self, leagues: &[u64]) -> Result<Vec<Fixture>, FootyError> {
+        let body = self.get(get_live_fixtures_url(&self.base_url, leagues)).await?;
+        parse_fixtures(&body)
+    }
+
+    pub async fn team_fixtures(&self, team_id: u64, season: u16, last: u8) -> Result<Vec<Fixture>, FootyError> {
+        let body = self.get(get_team_url(&self.base_url, team_id, season, last)).await?;
+        parse_fixtures(&body)
+    }
+
+    pub async fn fixtures_in_range(&self, league_id: u64, season: u16, from: &str, to: &str) -> Result<Vec<Fixture>, FootyError> {
+        let body = self.get(get_fixtures_url_by_date_range(&self.base_url, league_id, season, from, to)).await?;
+        parse_fixtures(&body)
+    }
+
+    // Every league and cup the team plays in that season
+    pub async fn team_leagues(&self, team_id: u64, season: u16) -> Result<Vec<LeagueInfo>, FootyError> {
+        let body = self.get(get_team_leagues_url(&self.base_url, team_id, season)).await?;
+        parse_leagues(&body)
+    }
+
+    pub async fn search_teams(&self, name: &str) -> Result<Vec<TeamInfo>, FootyError> {
+        let body = self.get(get_team_search_url(&self.base_url, name)).await?;
+        parse_teams(&body)
+    }
+
+    pub async fn team(&self, team_id: u64) -> Result<Vec<TeamInfo>, FootyError> {
+        let body = self.get(get_team_by_id_url(&self.base_url, team_id)).await?;
+        parse_teams(&body)
+    }
+
+    // API-Football matches the search against both league and country names
+    pub async fn search_leagues(&self, query: &str) -> Result<Vec<LeagueInfo>, FootyError> {
+        let body = self.get(get_league_search_url(&self.base_url, query)).await?;
+        parse_leagues(&body)
+    }
+
+    pub async fn league(&self, league_id: u64) -> Result<Option<LeagueInfo>, FootyError> {
+        let body = self.get(get_league_url(&self.base_url, league_id)).await?;
+        Ok(parse_leagues(&body)?.into_iter().next())
+    }
+
+    pub async fn standings(&self, league_id: u64, season: u16) -> Result<Vec<Vec<TeamStanding>>, FootyError> {
+        let body = self.get(get_standings_url(&self.base_url, league_id, season)).await?;
+        parse_standings(&body)
+    }
+
+    // Raw bytes from outside the API, like team logos: no key, cache or quota, but the same
+    // timeout and retries
+    pub async fn download(&self, url: &str) -> Result<Vec<u8>, FootyError> {
+        self.with_retries(|| self.download_once(url)).await
+    }
+
+    async fn get(&self, url: String) -> Result<String, FootyError> {
+        match (&self.cache, self.cache_mode) {
+            (Some(cache), CacheMode::Normal) => {
+                if let Some(body) = cache.get_fresh(&url) { return Ok(body); }
+            },
+            (Some(cache), CacheMode::Offline) => {
+                return cache.get_any(&url)
+                    .ok_or_else(|| FootyError::Config(format!("Nothing cached for {} yet. Run without --offline first", url)));
+            },
+            (None, CacheMode::Offline) => return Err(FootyError::Config(String::from("No cache directory available for --offline"))),
+            (_, CacheMode::Refresh) | (None, CacheMode::Normal) => {},
+        }
+
+        let body = self.fetch(&url).await?;
+
+        if let Some(cache) = &self.cache {
+            if let Err(err) = cache.put(&url, &body) {
+                log::warn!("Couldn't write response cache: {}", err);
+            }
+        }
+
+        Ok(body)
+    }
+
+    async fn fetch(&self, url: &str) -> Result<String, FootyError> {
+        self.with_retries(|| self.fetch_once(url)).await
+    }
+
+    async fn with_retries<T, F, Fut>(&self, mut attempt_once: F) -> Result<T, FootyError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, FetchError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let (err, wait) = match attempt_once().await {
+                Ok(body) => return Ok(body),
+                Err(FetchError::Fatal(err)) => return Err(err),
+                Err(FetchError::Transient(err, wait)) => (err, wait),
+            };
+
+            let delay = match self.retry.delay_for(attempt, wait) {
+                Some(delay) => delay,
+                None => return Err(err),
+            };
+            log::warn!("{}. Retrying in {:.1}s", err, delay.as_secs_f64());
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn fetch_once(&self, url: &str) -> Result<String, FetchError> {
+        let mut request = self.http.get(url)
+            .header(self.provider.auth_header(), &self.key);
+
+        // api-sports.io identifies the API by its hostname alone
+        if self.provider == Provider::RapidApi {
+            request = request.header("X-RapidAPI-Host", self.provider.host());
+        }
+
+        let response = request.send().await.map_err(network_error)?;
+
+        if let Some(quota) = &self.quota {
+            let endpoint = url.strip_prefix(&self.base_url).unwrap_or(url);
+            if let Err(err) = quota.record(endpoint, response.headers()) {
+                log::warn!("Couldn't save API quota: {}", err);
+            }
+        }
+
+        let status = response.status();
+        let wait = retry_after(response.headers());
+        let body = response.text().await.map_err(network_error)?;
+
+        if status.is_success() { return Ok(body); }
+
+        let err = status_error(status, &body);
+        // a spent daily allowance won't come back however often we ask
+        let spent_for_the_day = matches!(&err, FootyError::Quota(message) if wait.is_none() && is_daily_limit(message));
+        if is_retryable_status(status) && !spent_for_the_day {
+            Err(FetchError::Transient(err, wait))
+        } else {
+            Err(FetchError::Fatal(err))
+        }
+    }
+
+    async fn download_once(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        let response = self.http.get(url).send().await.map_err(network_error)?;
+
+        let status = response.status();
+        if !status.is_success() {
+            // not the API itself, so no "The football API returned" wording
+            let err = FootyError::Config(format!("Couldn't download {} ({})", url, status));
+            return Err(if is_retryable_status(status) { FetchError::Transient(err, retry_after(response.headers())) } else { FetchError::Fatal(err) });
+        }
+        Ok(response.bytes().await.map_err(network_error)?.to_vec())
+    }
+}
+
+fn build_http_client(timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .build()
+        .unwrap_or_default()
+}
+
+fn network_error(err: reqwest::Error) -> FetchError {
+    if is_retryable_error(&err) {
+        FetchError::Transient(FootyError::Network(err), None)
+    } else {
+        FetchError::Fatal(FootyError::Network(err))
+    }
+}
+
+fn is_daily_limit(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("daily") || message.contains("monthly")
+}
+
+// RapidAPI rejects bad keys and exhausted plans at the HTTP level, with {"message": "..."}
+fn status_error(status: StatusCode, body: &str) -> FootyError {
+    let message = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|data| data["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| status.to_string());
+
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => FootyError::Auth(message),
+        StatusCode::TOO_MANY_REQUESTS => FootyError::Quota(message),
+        _ => FootyError::Api(format!("{} ({})", message, status)),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{env, io::{Read, Write}, net::TcpListener, sync::mpsc, thread};
+
+    use super::*;
+
+    pub(crate) fn serve_once(body: &'static str) -> (String, mpsc::Receiver<String>) {
+        serve_once_with_headers(&[], body)
+    }
+
+    pub(crate) fn serve_once_with_headers(headers: &[(&'static str, &'static str)], body: &'static str) -> (String, mpsc::Receiver<String>) {
+        serve_sequence(vec![(200, headers.to_vec(), body)])
+    }
+
+    pub(crate) type MockResponse = (u16, Vec<(&'static str, &'static str)>, &'static str);
+
+    // Minimal HTTP server: answers one request per (status, headers, body) in order
+    // and hands the raw request text back for assertions.
+    pub(crate) fn serve_sequence(responses: Vec<MockResponse>) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            for (status, headers, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 8192];
+                let n = stream.read(&mut buf).unwrap();
+                let _ = tx.send(String::from_utf8_lossy(&buf[..n]).to_string());
+
+                let extra_headers: String = headers.iter()
+                    .map(|(name, value)| format!("{}: {}\r\n", name, value))
+                    .collect();
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+                    status,
+                    body.len(),
+                    extra_headers,
+                    body,
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (format!("http://{}", addr), rx)
+    }
+
+    fn fast_retries(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            timeout: Duration::from_millis(500),
+        }
+    }
+
+    const FIXTURES_BODY: &str = r#"{"response": [{
+        "fixture": {"id": 1, "referee": "M. Oliver", "timezone": "UTC", "date": "2024-08-17T14:00:00+00:00", "timestamp": 1723903200,
+            "periods": {"first": null, "second": null}, "venue": {"id": 550, "name": "Anfield", "city": "Liverpool"},
+            "status": {"long": "Not Started", "short": "NS", "elapsed": null}},
+        "league": {"id": 39, "name": "Premier League", "country": "England", "logo": "", "flag": null, "season": 2024, "round": null},
+        "teams": {"home": {"id": 40, "name": "Liverpool", "logo": "", "winner": null}, "away": {"id": 49, "name": "Chelsea", "logo": "", "winner": null}},
+        "goals": {"home": null, "away": null}
+    }]}"#;
+
+    #[tokio::test]
+    async fn test_fixtures_by_league_against_mock_server() {
+        let (base_url, requests) = serve_once(FIXTURES_BODY);
+        let client = ApiFootballClient::new(Provider::RapidApi, String::from("test-key")).with_base_url(&base_url);
+
+        let fixtures = client.fixtures_by_league(39, "2024-08-17", 2024).await.unwrap();
+        let request = requests.recv().unwrap().to_lowercase();
+
+        assert_eq!(fixtures.len(), 1);
+        assert_eq!(fixtures[0].teams.home.name, "Liverpool");
+        assert!(request.starts_with("get /fixtures?league=39&season=2024&date=2024-08-17 "));
+        assert!(request.contains("x-rapidapi-key: test-key"));
+    }
+
+    #[tokio::test]
+    async fn test_standings_against_mock_server() {
+        let (base_url, _requests) = serve_once(r#"{"response": [{"league": {"standings": [[]]}}]}"#);
+        let client = ApiFootballClient::new(Provider::RapidApi, String::from("test-key")).with_base_url(&base_url);
+
+        let standings = client.standings(39, 2024).await.unwrap();
+
+        assert_eq!(standings.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_api_sports_provider_sends_its_own_auth_header() {
+        let (base_url, requests) = serve_once(FIXTURES_BODY);
+        let client = ApiFootballClient::new(Provider::ApiSports, String::from("test-key")).with_base_url(&base_url);
+
+        client.live_fixtures(&[39, 140]).await.unwrap();
+        let request = requests.recv().unwrap().to_lowercase();
+
+        assert!(request.starts_with("get /fixtures?live=39-140 "));
+        assert!(request.contains("x-apisports-key: test-key"));
+        assert!(!request.contains("x-rapidapi"));
+    }
+
+    #[test]
+    fn test_provider_base_urls() {
+        assert_eq!(Provider::RapidApi.base_url(), "https://api-football-v1.p.rapidapi.com/v3");
+        assert_eq!(Provider::ApiSports.base_url(), "https://v3.football.api-sports.io");
+        assert_eq!(Provider::from_name("API-Sports").unwrap(), Provider::ApiSports);
+        assert!(Provider::from_name("espn").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cached_responses_skip_the_network() {
+        let dir = env::temp_dir().join(format!("footy-client-cache-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (base_url, requests) = serve_once(FIXTURES_BODY);
+        let client = ApiFootballClient::new(Provider::RapidApi, String::from("test-key"))
+            .with_base_url(&base_url)
+            .with_cache(Some(ResponseCache::new(dir.clone())));
+
+        let fetched = client.fixtures_by_league(39, "2024-08-17", 2024).await.unwrap();
+        requests.recv().unwrap();
+
+        // the one-shot server is gone, so this has to come from disk
+        let offline = client.with_cache_mode(CacheMode::Offline);
+        let cached = offline.fixtures_by_league(39, "2024-08-17", 2024).await.unwrap();
+
+        assert_eq!(fetched.len(), cached.len());
+        assert!(offline.fixtures_by_league(140, "2024-08-17", 2024).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_headers_are_recorded() {
+        let path = env::temp_dir().join(format!("footy-client-quota-test-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let headers = [("x-ratelimit-requests-limit", "100"), ("x-ratelimit-requests-remaining", "57")];
+        let (base_url, _requests) = serve_once_with_headers(&headers, FIXTURES_BODY);
+        let client = ApiFootballClient::new(Provider::RapidApi, String::from("test-key"))
+            .with_base_url(&base_url)
+            .with_quota_tracker(Some(QuotaTracker::new(path.clone())));
+
+        client.team_fixtures(40, 2024, 2).await.unwrap();
+        let state = QuotaTracker::new(path).load();
+
+        assert_eq!(state.remaining_today(), Some(57));
+        assert_eq!(state.limit, Some(100));
+        assert_eq!(state.calls[0].endpoint, "/fixtures?season=2024&team=40&last=2");
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let (base_url, requests) = serve_sequence(vec![
+            (503, vec![], "upstream unavailable"),
+            (429, vec![("Retry-After", "0")], r#"{"message": "Too many requests"}"#),
+            (200, vec![], FIXTURES_BODY),
+        ]);
+        let client = ApiFootballClient::new(Provider::RapidApi, String::from("test-key"))
+            .with_base_url(&base_url)
+            .with_retry_policy(fast_retries(3));
+
+        let fixtures = client.live_fixtures(&[39]).await.unwrap();
+
+        assert_eq!(fixtures.len(), 1);
+        assert_eq!(requests.try_iter().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_downloads_are_retried_without_the_api_key() {
+        let (base_url, requests) = serve_sequence(vec![(502, vec![], "bad gateway"), (200, vec![], "PNG"), (404, vec![], "")]);
+        let client = ApiFootballClient::new(Provider::RapidApi, String::from("test-key")).with_retry_policy(fast_retries(3));
+
+        let logo = client.download(&format!("{}/football/teams/40.png", base_url)).await.unwrap();
+        let missing = client.download(&format!("{}/football/teams/0.png", base_url)).await;
+        let requests: Vec<String> = requests.try_iter().collect();
+
+        assert_eq!(logo, b"PNG");
+        assert!(matches!(missing, Err(FootyError::Config(message)) if message.contains("404")));
+        assert_eq!(requests.len(), 3);
+        assert!(!requests[1].to_lowercase().contains("test-key"));
+    }
+
+    #[tokio::test]
+    async fn test_permanent_failures_are_not_retried() {
+        let (base_url, requests) = serve_sequence(vec![
+            (403, vec![], r#"{"message": "You are not subscribed to this API."}"#),
+        ]);
+        let client = ApiFootballClient::new(Provider::RapidApi, String::from("bad-key"))
+            .with_base_url(&base_url)
+            .with_retry_policy(fast_retries(3));
+
+        let result = client.live_fixtures(&[39]).await;
+
+        assert!(matches!(result, Err(FootyError::Auth(message)) if message.contains("not subscribed")));
+        assert_eq!(requests.try_iter().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        // accepts the connection but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let _held = listener.accept();
+            thread::sleep(Duration::from_secs(2));
+        });
+        let client = ApiFootballClient::new(Provider::RapidApi, String::from("test-key"))
+            .with_base_url(&base_url)
+            .with_retry_policy(RetryPolicy { timeout: Duration::from_millis(100), ..fast_retries(0) });
+
+        let result = client.live_fixtures(&[39]).await;
+
+        assert!(matches!(result, Err(FootyError::Network(err)) if err.is_timeout()));
+    }
+}
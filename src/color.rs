@@ -0,0 +1,249 @@
+use std::{cell::OnceCell, env, io::{self, IsTerminal}};
+
+use clap::ValueEnum;
+
+use colored::Color;
+
+use crate::theme::{self, Theme};
+
+// When to colour output. Auto colours terminals only, and never when NO_COLOR is set.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+// How many colours the terminal can show; team colours are quantised to fit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorDepth {
+    Ansi16 = 1,
+    Ansi256 = 2,
+    TrueColor = 3,
+}
+
+// xterm's defaults for the 16 basic colours
+const ANSI16: [(Color, [u8; 3]); 16] = [
+    (Color::Black, [0, 0, 0]),
+    (Color::Red, [205, 0, 0]),
+    (Color::Green, [0, 205, 0]),
+    (Color::Yellow, [205, 205, 0]),
+    (Color::Blue, [0, 0, 238]),
+    (Color::Magenta, [205, 0, 205]),
+    (Color::Cyan, [0, 205, 205]),
+    (Color::White, [229, 229, 229]),
+    (Color::BrightBlack, [127, 127, 127]),
+    (Color::BrightRed, [255, 0, 0]),
+    (Color::BrightGreen, [0, 255, 0]),
+    (Color::BrightYellow, [255, 255, 0]),
+    (Color::BrightBlue, [92, 92, 255]),
+    (Color::BrightMagenta, [255, 0, 255]),
+    (Color::BrightCyan, [0, 255, 255]),
+    (Color::BrightWhite, [255, 255, 255]),
+];
+
+// Levels of the 6x6x6 colour cube in the 256-colour palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// What the terminal can show, worked out once by `init` and then passed to whatever paints
+#[derive(Clone, Debug, PartialEq)]
+pub struct Terminal {
+    pub colorize: bool,
+    pub depth: ColorDepth,
+    // Team colours are adjusted for contrast against the theme's background when there is one.
+    // Asking the terminal for it can take a moment, so that waits until something is painted.
+    theme: Option<Theme>,
+    background: OnceCell<Option<[u8; 3]>>,
+}
+
+impl Default for Terminal {
+    // no colour at all, as for pipes and files
+    fn default() -> Terminal {
+        Terminal { colorize: false, depth: ColorDepth::TrueColor, theme: None, background: OnceCell::new() }
+    }
+}
+
+// Decides once whether and how to colour output. Prompts still bold through `colored`, so its
+// override is set here too, which is what keeps their escapes out of pipes and files.
+pub fn init(choice: ColorChoice, enabled_in_config: bool, theme: Theme) -> Terminal {
+    let colorize = enabled_in_config && match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => no_color_unset() && io::stdout().is_terminal() && env::var("TERM").map_or(true, |term| term != "dumb"),
+    };
+    colored::control::set_override(colorize);
+    if !colorize { return Terminal::default(); }
+
+    Terminal::new(detect_depth(env::var("COLORTERM").ok().as_deref(), env::var("TERM").ok().as_deref()), Some(theme))
+}
+
+// https://no-color.org: set to anything but an empty string
+fn no_color_unset() -> bool {
+    env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+fn detect_depth(colorterm: Option<&str>, term: Option<&str>) -> ColorDepth {
+    match (colorterm, term) {
+        (Some("truecolor" | "24bit"), _) => ColorDepth::TrueColor,
+        (_, Some(term)) if term.contains("256color") => ColorDepth::Ansi256,
+        _ => ColorDepth::Ansi16,
+    }
+}
+
+impl Terminal {
+    // A colour terminal; without a theme, colours are used as they are
+    pub fn new(depth: ColorDepth, theme: Option<Theme>) -> Terminal {
+        Terminal { colorize: true, depth, theme, background: OnceCell::new() }
+    }
+
+    fn background(&self) -> Option<[u8; 3]> {
+        *self.background.get_or_init(|| self.theme.map(Theme::background))
+    }
+
+    // Writes `text` in `rgb`, made readable on the terminal's background and then matched to
+    // the nearest colour the terminal has
+    pub fn paint(&self, text: &str, rgb: [u8; 3]) -> String {
+        let rgb = match self.background() {
+            Some(background) => theme::ensure_contrast(rgb, background, theme::MIN_CONTRAST),
+            None => rgb,
+        };
+        self.paint_exact(text, rgb)
+    }
+
+    // Like `paint`, but the colour is only matched to the terminal, never adjusted for contrast
+    pub fn paint_exact(&self, text: &str, rgb: [u8; 3]) -> String {
+        if !self.colorize { return text.to_string(); }
+
+        // Escapes are written by hand: `colored` checks its global override, has no 256-colour
+        // support, and newer versions turn truecolor into ANSI-16 unless COLORTERM says otherwise
+        let [r, g, b] = rgb;
+        let code = match self.depth {
+            ColorDepth::TrueColor => format!("38;2;{};{};{}", r, g, b),
+            ColorDepth::Ansi256 => format!("38;5;{}", nearest_ansi256(rgb)),
+            ColorDepth::Ansi16 => nearest_ansi16(rgb).to_fg_str().to_string(),
+        };
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+
+    pub fn bold(&self, text: &str) -> String {
+        if self.colorize { format!("\x1b[1m{}\x1b[0m", text) } else { text.to_string() }
+    }
+
+    // A team's primary colour, unless only its secondary one is readable on this background
+    pub fn most_readable(&self, primary: [u8; 3], secondary: Option<[u8; 3]>) -> [u8; 3] {
+        let (Some(secondary), Some(background)) = (secondary, self.background()) else { return primary };
+        let readable = |rgb: [u8; 3]| theme::contrast_ratio(rgb, background) >= theme::MIN_CONTRAST;
+
+        if !readable(primary) && readable(secondary) { secondary } else { primary }
+    }
+}
+
+// "(200, 16, 46)" as in the old id_rgb.csv, or "#c8102e"; None for anything else
+pub fn parse_rgb(value: &str) -> Option<[u8; 3]> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() { return None; }
+        let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+        return Some([channel(0)?, channel(2)?, channel(4)?]);
+    }
+
+    let values: Vec<&str> = value
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .split(',')
+        .collect();
+
+    match values.as_slice() {
+        [r, g, b] => Some([r.trim().parse().ok()?, g.trim().parse().ok()?, b.trim().parse().ok()?]),
+        _ => None,
+    }
+}
+
+fn nearest_ansi16(rgb: [u8; 3]) -> Color {
+    ANSI16.iter()
+        .min_by_key(|(_, candidate)| distance(rgb, *candidate))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::White)
+}
+
+// Palette index of the closest cube colour or grey
+fn nearest_ansi256(rgb: [u8; 3]) -> u8 {
+    let level = |value: u8| -> usize {
+        (0..CUBE_LEVELS.len()).min_by_key(|index| CUBE_LEVELS[*index].abs_diff(value)).unwrap_or(0)
+    };
+    let [r, g, b] = rgb.map(level);
+    let cube = [CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]];
+    let cube_index = 16 + 36 * r + 6 * g + b;
+
+    // greys run from 8 to 238 in steps of 10
+    let average = (rgb.iter().map(|value| *value as usize).sum::<usize>() / 3) as u8;
+    let grey_step = (average.saturating_sub(3) / 10).min(23);
+    let grey = 8 + 10 * grey_step;
+
+    if distance(rgb, [grey; 3]) < distance(rgb, cube) {
+        232 + grey_step
+    } else {
+        cube_index as u8
+    }
+}
+
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter().zip(b.iter()).map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rgb() {
+        assert_eq!(parse_rgb("(200, 16, 46)"), Some([200, 16, 46]));
+        assert_eq!(parse_rgb("#C8102e"), Some([200, 16, 46]));
+        assert_eq!(parse_rgb("(300, 16, 46)"), None);
+        assert_eq!(parse_rgb("(200, 16)"), None);
+        assert_eq!(parse_rgb("#c8102"), None);
+        assert_eq!(parse_rgb("white"), None);
+    }
+
+    #[test]
+    fn test_detect_depth() {
+        assert_eq!(detect_depth(Some("truecolor"), Some("xterm-256color")), ColorDepth::TrueColor);
+        assert_eq!(detect_depth(None, Some("xterm-256color")), ColorDepth::Ansi256);
+        assert_eq!(detect_depth(None, Some("xterm")), ColorDepth::Ansi16);
+        assert_eq!(detect_depth(None, None), ColorDepth::Ansi16);
+    }
+
+    #[test]
+    fn test_quantises_team_colours() {
+        // Liverpool red, Chelsea blue, Newcastle black and a mid grey
+        assert_eq!(nearest_ansi256([200, 16, 46]), 160);
+        assert_eq!(nearest_ansi256([3, 70, 148]), 24);
+        assert_eq!(nearest_ansi256([0, 0, 0]), 16);
+        assert_eq!(nearest_ansi256([128, 128, 128]), 244);
+
+        assert_eq!(nearest_ansi16([200, 16, 46]), Color::Red);
+        assert_eq!(nearest_ansi16([3, 70, 148]), Color::Blue);
+        assert_eq!(nearest_ansi16([250, 250, 250]), Color::BrightWhite);
+    }
+
+    #[test]
+    fn test_terminal_paints_to_its_depth() {
+        let terminal = |depth| Terminal::new(depth, None);
+        assert_eq!(terminal(ColorDepth::TrueColor).paint("LIV", [200, 16, 46]), "\x1b[38;2;200;16;46mLIV\x1b[0m");
+        assert_eq!(terminal(ColorDepth::Ansi256).paint("LIV", [200, 16, 46]), "\x1b[38;5;160mLIV\x1b[0m");
+        assert_eq!(terminal(ColorDepth::Ansi16).paint("LIV", [200, 16, 46]), "\x1b[31mLIV\x1b[0m");
+        assert_eq!(Terminal::default().paint("LIV", [200, 16, 46]), "LIV");
+        assert_eq!(terminal(ColorDepth::Ansi16).bold("2 - 1"), "\x1b[1m2 - 1\x1b[0m");
+        assert_eq!(Terminal::default().bold("2 - 1"), "2 - 1");
+
+        // Newcastle's black is lightened on a dark background, unless it's a badge
+        let dark = Terminal::new(ColorDepth::TrueColor, Some(Theme::Dark));
+        // the background is only looked up once something is painted
+        assert_eq!(dark.background.get(), None);
+        assert_ne!(dark.paint("NEW", [0, 0, 0]), dark.paint_exact("NEW", [0, 0, 0]));
+        assert_eq!(dark.background.get(), Some(&Some([0, 0, 0])));
+        assert_eq!(dark.most_readable([0, 0, 0], Some([255, 255, 255])), [255, 255, 255]);
+        assert_eq!(Terminal::default().most_readable([0, 0, 0], Some([255, 255, 255])), [0, 0, 0]);
+    }
+}
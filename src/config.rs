@@ -0,0 +1,466 @@
+use std::{collections::{BTreeMap, HashMap}, env, fs, path::{Path, PathBuf}, time::Duration};
+
+use chrono_tz::Tz;
+
+use serde::{Serialize, Deserialize};
+
+use clap::ValueEnum;
+
+use toml_edit::{Array, DocumentMut, Item, TableLike, Value};
+
+use crate::{atomic_file::write_atomic, client::Provider, color, error::FootyError, layout::Layout, retry::RetryPolicy, theme::Theme, CommandType, OutputFormat};
+
+const DEFAULT_PREFERRED_LEAGUES: [u64; 4] = [39, 135, 78, 140];
+const DEFAULT_LIVE_LEAGUES: [u64; 8] = [39, 140, 88, 78, 135, 61, 94, 253];
+const DEFAULT_KEY_ENV: &str = "FOOTY_API_KEY";
+const DEFAULT_COLORS_FILE: &str = "./team_colors.csv";
+const DEFAULT_TEAMS_FILE: &str = "./teams.csv";
+const MAX_RETRIES_LIMIT: u32 = 10;
+
+// Everything footy needs at runtime, resolved from config.toml, the environment and defaults
+#[derive(Debug)]
+pub struct Settings {
+    pub teams: HashMap<String, u64>,
+    pub teams_file: PathBuf,
+    pub preferred_leagues: Vec<u64>,
+    pub full_leagues: Vec<u64>,
+    pub default: CommandType,
+    pub timezone: Option<Tz>,
+    pub format: OutputFormat,
+    pub layout: Layout,
+    pub api: ApiSettings,
+    pub colors: ColorSettings,
+    // the file these came from, and where `footy leagues` saves changes
+    pub config_path: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct ApiSettings {
+    pub key: Option<String>,
+    // where the key should have come from, for the "missing key" message
+    pub key_source: String,
+    pub provider: Provider,
+    pub base_url: Option<String>,
+    pub retry: RetryPolicy,
+}
+
+#[derive(Debug, Clone)]
+pub struct ColorSettings {
+    pub enabled: bool,
+    pub file: PathBuf,
+    pub theme: Theme,
+    // a block of the team's colours before each name
+    pub badges: bool,
+    // team ID -> colour, used instead of the colours file
+    pub overrides: HashMap<u64, [u8; 3]>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            teams: HashMap::new(),
+            teams_file: PathBuf::from(DEFAULT_TEAMS_FILE),
+            preferred_leagues: DEFAULT_PREFERRED_LEAGUES.to_vec(),
+            full_leagues: DEFAULT_LIVE_LEAGUES.to_vec(),
+            default: CommandType::Schedule,
+            timezone: None,
+            format: OutputFormat::Table,
+            layout: Layout::Auto,
+            api: ApiSettings {
+                key: None,
+                key_source: DEFAULT_KEY_ENV.to_string(),
+                provider: Provider::default(),
+                base_url: None,
+                retry: RetryPolicy::default(),
+            },
+            colors: ColorSettings {
+                enabled: true,
+                file: PathBuf::from(DEFAULT_COLORS_FILE),
+                theme: Theme::Auto,
+                badges: false,
+                overrides: HashMap::new(),
+            },
+            config_path: None,
+        }
+    }
+}
+
+// On-disk shape of config.toml. Every field is optional so a config only
+// needs the settings someone actually wants to change.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub teams_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api: Option<ApiConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leagues: Option<LeaguesConfig>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub teams: BTreeMap<String, u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colors: Option<ColorsConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ApiConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_env: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LeaguesConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred: Option<Vec<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub live: Option<Vec<u64>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ColorsConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub badges: Option<bool>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub teams: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+}
+
+// FOOTY_CONFIG wins, then $XDG_CONFIG_HOME/footy/config.toml (or the platform equivalent)
+pub fn default_config_path() -> Option<PathBuf> {
+    match env::var("FOOTY_CONFIG") {
+        Ok(path) => Some(PathBuf::from(path)),
+        Err(_) => Some(dirs::config_dir()?.join("footy").join("config.toml")),
+    }
+}
+
+// A missing file at the default location just means "use the defaults",
+// but a file passed with --config has to exist.
+pub fn load_config_file(explicit_path: Option<&Path>) -> Result<ConfigFile, FootyError> {
+    let path = match explicit_path {
+        Some(path) => path.to_path_buf(),
+        None => match default_config_path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(ConfigFile::default()),
+        },
+    };
+
+    read_config_file(&path)
+}
+
+fn read_config_file(path: &Path) -> Result<ConfigFile, FootyError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| FootyError::Config(format!("Couldn't read config file {}: {}", path.display(), err)))?;
+
+    toml::from_str(&contents)
+        .map_err(|err| FootyError::Config(format!("Invalid config file {}: {}", path.display(), err)))
+}
+
+pub fn load_settings(explicit_path: Option<&Path>) -> Result<Settings, FootyError> {
+    let mut settings = Settings::from_config(load_config_file(explicit_path)?)?;
+    settings.config_path = explicit_path.map(Path::to_path_buf).or_else(default_config_path);
+    Ok(settings)
+}
+
+// Rewrites the league lists in the config, creating the file if needed. Only [leagues] is
+// touched, so the rest of the file keeps its comments and layout.
+pub fn save_leagues(path: &Path, preferred: Vec<u64>, live: Vec<u64>) -> Result<(), FootyError> {
+    let preferred = validate_leagues("leagues.preferred", preferred)?;
+    let live = validate_leagues("leagues.live", live)?;
+
+    let contents = if path.exists() {
+        // a broken config is reported rather than rewritten
+        read_config_file(path)?;
+        fs::read_to_string(path)?
+    } else {
+        String::new()
+    };
+    let mut document: DocumentMut = contents.parse()
+        .map_err(|err| FootyError::Config(format!("Invalid config file {}: {}", path.display(), err)))?;
+
+    let leagues = document.entry("leagues").or_insert(toml_edit::table()).as_table_like_mut()
+        .ok_or_else(|| FootyError::Config(format!("leagues in {} has to be a table", path.display())))?;
+    set_leagues(leagues, "preferred", &preferred);
+    set_leagues(leagues, "live", &live);
+
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+    write_atomic(path, document.to_string().as_bytes())?;
+    Ok(())
+}
+
+// Replaces the value in place so comments around the key stay where they were
+fn set_leagues(table: &mut dyn TableLike, key: &str, leagues: &[u64]) {
+    let array: Array = leagues.iter().map(|league_id| *league_id as i64).collect();
+    match table.get_mut(key).and_then(Item::as_value_mut) {
+        Some(value) => {
+            let decor = value.decor().clone();
+            *value = Value::Array(array);
+            *value.decor_mut() = decor;
+        },
+        None => { table.insert(key, toml_edit::value(array)); },
+    }
+}
+
+impl Settings {
+    // Validates the file and layers environment overrides on top of it
+    pub fn from_config(config: ConfigFile) -> Result<Settings, FootyError> {
+        let mut settings = Settings::default();
+
+        if let Some(name) = &config.default_command {
+            settings.default = CommandType::from_name(name)
+                .ok_or_else(|| invalid(format!("default_command '{}' is not a footy command", name)))?;
+        }
+
+        if let Some(name) = &config.timezone {
+            settings.timezone = Some(name.parse::<Tz>()
+                .map_err(|_| invalid(format!("Unknown timezone '{}'. Use an IANA name like America/Chicago", name)))?);
+        }
+
+        let leagues = config.leagues.unwrap_or_default();
+        if let Some(preferred) = leagues.preferred {
+            settings.preferred_leagues = validate_leagues("leagues.preferred", preferred)?;
+        }
+        if let Some(live) = leagues.live {
+            settings.full_leagues = validate_leagues("leagues.live", live)?;
+        }
+
+        if let Some(file) = config.teams_file { settings.teams_file = file; }
+        if let Ok(file) = env::var("CONFIG_PATH") { settings.teams_file = PathBuf::from(file); }
+
+        for (name, id) in config.teams {
+            if id == 0 { return Err(invalid(format!("Team '{}' needs a non-zero ID", name))); }
+            settings.teams.insert(name, id);
+        }
+
+        let output = config.output.unwrap_or_default();
+        if let Some(format) = output.format {
+            settings.format = match format.to_lowercase().as_str() {
+                "table" => OutputFormat::Table,
+                "json" => OutputFormat::Json,
+                _ => return Err(invalid(format!("output.format '{}' must be 'table' or 'json'", format))),
+            };
+        }
+        if let Some(layout) = output.layout {
+            settings.layout = Layout::from_str(&layout, true)
+                .map_err(|_| invalid(format!("output.layout '{}' must be 'auto', 'compact', 'normal' or 'wide'", layout)))?;
+        }
+
+        let colors = config.colors.unwrap_or_default();
+        settings.colors.enabled = colors.enabled.unwrap_or(true);
+        settings.colors.badges = colors.badges.unwrap_or(false);
+        if let Some(file) = colors.file { settings.colors.file = file; }
+        if let Ok(file) = env::var("RGB_PATH") { settings.colors.file = PathBuf::from(file); }
+        if let Some(theme) = colors.theme {
+            settings.colors.theme = Theme::from_str(&theme, true)
+                .map_err(|_| invalid(format!("colors.theme '{}' must be 'auto', 'dark' or 'light'", theme)))?;
+        }
+        for (team_id, value) in colors.teams {
+            let id = team_id.parse::<u64>().ok().filter(|id| *id != 0)
+                .ok_or_else(|| invalid(format!("colors.teams keys must be team IDs, got '{}'", team_id)))?;
+            let rgb = color::parse_rgb(&value)
+                .ok_or_else(|| invalid(format!("Colour for team {} must look like \"#c8102e\" or \"(200, 16, 46)\", got '{}'", id, value)))?;
+            settings.colors.overrides.insert(id, rgb);
+        }
+
+        settings.api = api_settings(config.api.unwrap_or_default())?;
+
+        Ok(settings)
+    }
+}
+
+fn api_settings(api: ApiConfig) -> Result<ApiSettings, FootyError> {
+    if api.key.is_some() && api.key_env.is_some() {
+        return Err(invalid(String::from("Set either api.key or api.key_env, not both")));
+    }
+
+    let (key, key_source) = match api.key {
+        Some(key) => (Some(key), String::from("api.key in your config file")),
+        None => {
+            let key_env = api.key_env.unwrap_or_else(|| DEFAULT_KEY_ENV.to_string());
+            (env::var(&key_env).ok(), key_env)
+        },
+    };
+
+    let provider = match env::var("FOOTY_API_PROVIDER").ok().or(api.provider) {
+        Some(name) => Provider::from_name(&name)?,
+        None => Provider::default(),
+    };
+
+    let mut retry = RetryPolicy::default();
+    if let Some(max_retries) = env_number("FOOTY_MAX_RETRIES")?.or(api.max_retries.map(u64::from)) {
+        if max_retries > MAX_RETRIES_LIMIT as u64 {
+            return Err(invalid(format!("max_retries can be at most {}", MAX_RETRIES_LIMIT)));
+        }
+        retry.max_retries = max_retries as u32;
+    }
+    if let Some(timeout) = env_number("FOOTY_TIMEOUT_SECS")?.or(api.timeout_secs) {
+        if timeout == 0 { return Err(invalid(String::from("timeout_secs must be at least 1"))); }
+        retry.timeout = Duration::from_secs(timeout);
+    }
+
+    Ok(ApiSettings {
+        key,
+        key_source,
+        provider,
+        // FOOTY_API_BASE_URL lets the CLI run against a local mock server
+        base_url: env::var("FOOTY_API_BASE_URL").ok().or(api.base_url),
+        retry,
+    })
+}
+
+fn validate_leagues(field: &str, leagues: Vec<u64>) -> Result<Vec<u64>, FootyError> {
+    if leagues.is_empty() {
+        return Err(invalid(format!("{} needs at least one league ID", field)));
+    }
+    for (index, league_id) in leagues.iter().enumerate() {
+        if *league_id == 0 {
+            return Err(invalid(format!("{} contains 0, which isn't a league ID", field)));
+        }
+        if leagues[..index].contains(league_id) {
+            return Err(invalid(format!("{} lists league {} twice", field, league_id)));
+        }
+    }
+    Ok(leagues)
+}
+
+fn env_number(name: &str) -> Result<Option<u64>, FootyError> {
+    match env::var(name) {
+        Ok(value) => value.trim().parse()
+            .map(Some)
+            .map_err(|_| invalid(format!("{} must be a whole number, got '{}'", name, value))),
+        Err(_) => Ok(None),
+    }
+}
+
+fn invalid(message: String) -> FootyError {
+    FootyError::Config(format!("Config error: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings_from(toml: &str) -> Result<Settings, FootyError> {
+        Settings::from_config(toml::from_str(toml).map_err(|err| FootyError::Config(err.to_string()))?)
+    }
+
+    #[test]
+    fn test_example_config_parses() {
+        let settings = settings_from(include_str!("../config.example.toml")).unwrap();
+
+        assert_eq!(settings.preferred_leagues, vec![39, 140, 135, 78]);
+        assert_eq!(settings.default, CommandType::Schedule);
+        assert_eq!(settings.timezone, Some(chrono_tz::America::Chicago));
+        assert_eq!(settings.teams.get("Liverpool"), Some(&40));
+        assert_eq!(settings.colors.overrides.get(&50), Some(&[108, 171, 221]));
+    }
+
+    #[test]
+    fn test_empty_config_uses_defaults() {
+        let settings = settings_from("").unwrap();
+
+        assert_eq!(settings.preferred_leagues, DEFAULT_PREFERRED_LEAGUES.to_vec());
+        assert_eq!(settings.full_leagues, DEFAULT_LIVE_LEAGUES.to_vec());
+        assert!(settings.colors.enabled);
+        assert_eq!(settings.layout, Layout::Auto);
+    }
+
+    #[test]
+    fn test_key_source_names_where_the_key_is_set() {
+        assert_eq!(settings_from("").unwrap().api.key_source, "FOOTY_API_KEY");
+        assert_eq!(settings_from("[api]\nkey_env = \"MY_FOOTBALL_KEY\"").unwrap().api.key_source, "MY_FOOTBALL_KEY");
+        assert_eq!(settings_from("[api]\nkey = \"abc\"").unwrap().api.key_source, "api.key in your config file");
+    }
+
+    #[test]
+    fn test_invalid_configs_are_rejected() {
+        assert!(settings_from("timezone = \"Mars/Olympus\"").is_err());
+        assert!(settings_from("default_command = \"fixtures\"").is_err());
+        assert!(settings_from("[leagues]\npreferred = []").is_err());
+        assert!(settings_from("[leagues]\nlive = [39, 39]").is_err());
+        assert!(settings_from("[output]\nformat = \"xml\"").is_err());
+        assert!(settings_from("[output]\nlayout = \"tiny\"").is_err());
+        assert!(settings_from("[colors]\ntheme = \"solarized\"").is_err());
+        assert!(settings_from("[colors.teams]\nNewcastle = \"#241f20\"").is_err());
+        assert!(settings_from("[colors.teams]\n50 = \"navy\"").is_err());
+        assert!(settings_from("[api]\nkey = \"abc\"\nkey_env = \"MY_KEY\"").is_err());
+        // typos shouldn't be silently ignored
+        assert!(settings_from("[leagues]\nprefered = [39]").is_err());
+    }
+
+    #[test]
+    fn test_config_file_round_trips() {
+        let config = ConfigFile {
+            leagues: Some(LeaguesConfig { preferred: Some(vec![39, 61]), live: None }),
+            ..ConfigFile::default()
+        };
+
+        let written = toml::to_string_pretty(&config).unwrap();
+
+        assert_eq!(toml::from_str::<ConfigFile>(&written).unwrap(), config);
+    }
+
+    #[test]
+    fn test_save_leagues_keeps_other_settings() {
+        let path = env::temp_dir().join(format!("footy-config-test-{}.toml", std::process::id()));
+        fs::write(&path, "timezone = \"Europe/London\"\n[teams]\nArsenal = 42\n").unwrap();
+
+        save_leagues(&path, vec![140, 39], vec![39, 140, 2]).unwrap();
+        let settings = Settings::from_config(read_config_file(&path).unwrap()).unwrap();
+        assert!(save_leagues(&path, vec![], vec![39]).is_err());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(settings.preferred_leagues, vec![140, 39]);
+        assert_eq!(settings.full_leagues, vec![39, 140, 2]);
+        assert_eq!(settings.timezone, Some(chrono_tz::Europe::London));
+        assert_eq!(settings.teams.get("Arsenal"), Some(&42));
+    }
+
+    #[test]
+    fn test_save_leagues_keeps_comments() {
+        let path = env::temp_dir().join(format!("footy-config-comments-{}.toml", std::process::id()));
+        let example = fs::read_to_string("config.example.toml").unwrap();
+        fs::write(&path, &example).unwrap();
+
+        save_leagues(&path, vec![140, 39], vec![39, 2]).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let expected = example
+            .replace("preferred = [39, 140, 135, 78]", "preferred = [140, 39]")
+            .replace("live = [39, 140, 88, 78, 135, 61, 94, 253]", "live = [39, 2]");
+        assert_eq!(saved, expected);
+    }
+}
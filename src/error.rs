@@ -0,0 +1,150 @@
+use std::{fmt, io};
+
+use serde_json::{Map, Value};
+
+#[derive(Debug)]
+pub enum FootyError {
+    // couldn't reach the API at all (DNS, TLS, timeouts, ...)
+    Network(reqwest::Error),
+    // key missing, invalid or suspended
+    Auth(String),
+    // daily or per-minute request limit hit
+    Quota(String),
+    // any other error the API reported
+    Api(String),
+    // the API answered with something we couldn't make sense of
+    Parse(String),
+    // bad settings, environment or local files
+    Config(String),
+    Io(io::Error),
+    // bad command line; clap formats these itself
+    Usage(clap::Error),
+}
+
+impl fmt::Display for FootyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FootyError::Network(err) => write!(f, "Couldn't reach the football API: {}", err),
+            FootyError::Auth(msg) => write!(f, "The API rejected your key: {}", msg),
+            FootyError::Quota(msg) => write!(f, "Out of API requests: {}. Run `footy quota` for today's usage", msg),
+            FootyError::Api(msg) => write!(f, "The football API returned an error: {}", msg),
+            FootyError::Parse(msg) => write!(f, "Couldn't understand the API response: {}", msg),
+            FootyError::Config(msg) => write!(f, "{}", msg),
+            FootyError::Io(err) => write!(f, "File error: {}", err),
+            FootyError::Usage(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl FootyError {
+    // Points a rejected key at where it came from: FOOTY_API_KEY, the variable named by
+    // api.key_env, or api.key in the config file
+    pub fn with_key_source(self, key_source: &str) -> FootyError {
+        match self {
+            FootyError::Auth(msg) => FootyError::Auth(format!("{}. Check {}", msg, key_source)),
+            err => err,
+        }
+    }
+}
+
+impl std::error::Error for FootyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FootyError::Network(err) => Some(err),
+            FootyError::Io(err) => Some(err),
+            FootyError::Usage(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for FootyError {
+    fn from(err: reqwest::Error) -> Self {
+        FootyError::Network(err)
+    }
+}
+
+impl From<io::Error> for FootyError {
+    fn from(err: io::Error) -> Self {
+        FootyError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for FootyError {
+    fn from(err: serde_json::Error) -> Self {
+        FootyError::Parse(err.to_string())
+    }
+}
+
+impl From<csv::Error> for FootyError {
+    fn from(err: csv::Error) -> Self {
+        if !err.is_io_error() { return FootyError::Parse(err.to_string()); }
+
+        match err.into_kind() {
+            csv::ErrorKind::Io(err) => FootyError::Io(err),
+            kind => FootyError::Parse(format!("{:?}", kind)),
+        }
+    }
+}
+
+// API-Football answers HTTP 200 even on failure, with the reason in `errors`:
+// `[]` when all is well, otherwise an object like {"token": "Error/Missing application key."}
+pub fn check_api_errors(data: &Map<String, Value>) -> Result<(), FootyError> {
+    let errors: Vec<(String, String)> = match data.get("errors") {
+        Some(Value::Object(errors)) => errors.iter()
+            .map(|(key, value)| (key.clone(), value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string())))
+            .collect(),
+        Some(Value::Array(errors)) => errors.iter()
+            .map(|value| (String::new(), value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string())))
+            .collect(),
+        _ => vec![],
+    };
+
+    let (key, message) = match errors.into_iter().next() {
+        Some(error) => error,
+        None => return Ok(()),
+    };
+
+    Err(match key.as_str() {
+        "token" | "access" => FootyError::Auth(message),
+        "requests" | "rateLimit" => FootyError::Quota(message),
+        _ => FootyError::Api(message),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors_of(json: &str) -> Result<(), FootyError> {
+        check_api_errors(&serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn test_error_envelope_kinds() {
+        assert!(errors_of(r#"{"errors": [], "response": []}"#).is_ok());
+        assert!(matches!(
+            errors_of(r#"{"errors": {"token": "Error/Missing application key."}, "response": []}"#),
+            Err(FootyError::Auth(_)),
+        ));
+        assert!(matches!(
+            errors_of(r#"{"errors": {"requests": "You have reached the request limit for the day"}, "response": []}"#),
+            Err(FootyError::Quota(_)),
+        ));
+        assert!(matches!(
+            errors_of(r#"{"errors": {"plan": "Free plans do not have access to this season"}, "response": []}"#),
+            Err(FootyError::Api(message)) if message.contains("Free plans"),
+        ));
+    }
+
+    #[test]
+    fn test_rejected_key_names_where_it_came_from() {
+        let rejected = || FootyError::Auth(String::from("Error/Invalid key"));
+        assert_eq!(
+            rejected().with_key_source("MY_FOOTBALL_KEY").to_string(),
+            "The API rejected your key: Error/Invalid key. Check MY_FOOTBALL_KEY",
+        );
+        assert!(rejected().with_key_source("api.key in your config file").to_string().ends_with("Check api.key in your config file"));
+        assert_eq!(FootyError::Api(String::from("down")).with_key_source("FOOTY_API_KEY").to_string(), "The football API returned an error: down");
+    }
+}
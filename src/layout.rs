@@ -0,0 +1,71 @@
+use clap::ValueEnum;
+
+// Narrower than this and team names give way to short codes
+const COMPACT_BELOW: u16 = 70;
+// Wide enough for the round, venue and referee columns too
+const WIDE_FROM: u16 = 140;
+
+// How much detail each row shows. Auto picks one from the terminal width.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Default)]
+pub enum Layout {
+    #[default]
+    Auto,
+    Compact,
+    Normal,
+    Wide,
+}
+
+impl Layout {
+    // Output that isn't going to a terminal (a pipe or a file) gets the normal layout
+    pub fn resolve(self) -> Layout {
+        self.resolve_for(terminal_size::terminal_size().map(|(width, _)| width.0))
+    }
+
+    fn resolve_for(self, terminal_width: Option<u16>) -> Layout {
+        match (self, terminal_width) {
+            (Layout::Auto, Some(width)) if width < COMPACT_BELOW => Layout::Compact,
+            (Layout::Auto, Some(width)) if width >= WIDE_FROM => Layout::Wide,
+            (Layout::Auto, _) => Layout::Normal,
+            (layout, _) => layout,
+        }
+    }
+}
+
+// Three-letter code for the compact layout. Fixtures don't carry the official codes, so this
+// is "ARS" for Arsenal and first letter + two of the last word for "Manchester City" -> "MCI".
+// Short prefixes like "FC" or "AC" are skipped.
+pub fn team_code(name: &str) -> String {
+    let words: Vec<&str> = name.split_whitespace().filter(|word| word.chars().count() > 2).collect();
+
+    let code: String = match words.as_slice() {
+        [] => name.chars().filter(|c| c.is_alphanumeric()).take(3).collect(),
+        [word] => word.chars().take(3).collect(),
+        [first, .., last] => first.chars().take(1).chain(last.chars().take(2)).collect(),
+    };
+    code.to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_layout_follows_terminal_width() {
+        assert_eq!(Layout::Auto.resolve_for(Some(60)), Layout::Compact);
+        assert_eq!(Layout::Auto.resolve_for(Some(100)), Layout::Normal);
+        assert_eq!(Layout::Auto.resolve_for(Some(200)), Layout::Wide);
+        assert_eq!(Layout::Auto.resolve_for(None), Layout::Normal);
+        assert_eq!(Layout::Wide.resolve_for(Some(40)), Layout::Wide);
+    }
+
+    #[test]
+    fn test_team_code() {
+        assert_eq!(team_code("Arsenal"), "ARS");
+        assert_eq!(team_code("Manchester City"), "MCI");
+        assert_eq!(team_code("Manchester United"), "MUN");
+        assert_eq!(team_code("AC Milan"), "MIL");
+        assert_eq!(team_code("Beşiktaş"), "BEŞ");
+        assert_eq!(team_code("PSV"), "PSV");
+        assert_eq!(team_code("FC"), "FC");
+    }
+}
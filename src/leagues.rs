@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use colored::Colorize;
+
+use serde::Serialize;
+
+use crate::{
+    build_client, cli::{LeagueAddArgs, LeagueMoveArgs}, config, error::FootyError, fetch_all, print_json, prompt_choice,
+    warn_about_quota, Command, LeagueInfo, LeaguesAction, OutputFormat, Settings, MIN_SEARCH_LENGTH,
+};
+
+#[derive(Serialize, Debug)]
+struct FollowedLeague {
+    id: u64,
+    name: Option<String>,
+    country: Option<String>,
+    preferred: bool,
+    live: bool,
+}
+
+pub async fn edit_leagues(cmd: &Command) -> Result<(), FootyError> {
+    match cmd.leagues_action.as_ref().unwrap_or(&LeaguesAction::List) {
+        LeaguesAction::List => list_leagues(cmd).await,
+        LeaguesAction::Add(args) => add_league(cmd, args).await,
+        LeaguesAction::Remove(args) => remove_league(cmd, &args.league).await,
+        LeaguesAction::Move(args) => move_league(cmd, args),
+    }
+}
+
+async fn list_leagues(cmd: &Command) -> Result<(), FootyError> {
+    let settings = &cmd.settings;
+    let ids = followed_ids(settings);
+
+    // names are only for display, so without a key or network we still list the IDs
+    let details = match league_details(cmd, &ids).await {
+        Ok(details) => details,
+        Err(err) => {
+            eprintln!("Couldn't look up league names: {}", err);
+            HashMap::new()
+        },
+    };
+
+    if cmd.format == OutputFormat::Json {
+        let followed: Vec<FollowedLeague> = ids.iter().map(|id| FollowedLeague {
+            id: *id,
+            name: details.get(id).map(|info| info.league.name.clone()),
+            country: details.get(id).map(|info| info.country.name.clone()),
+            preferred: settings.preferred_leagues.contains(id),
+            live: settings.full_leagues.contains(id),
+        }).collect();
+        print_json(&followed);
+        return Ok(());
+    }
+
+    println!("Preferred leagues (schedule, standings)");
+    for (index, league_id) in settings.preferred_leagues.iter().enumerate() {
+        println!("{:>3}. {}", index + 1, describe(*league_id, &details));
+    }
+    println!("\nLive leagues (live)");
+    for league_id in settings.full_leagues.iter() {
+        println!("     {}", describe(*league_id, &details));
+    }
+    Ok(())
+}
+
+async fn add_league(cmd: &Command, args: &LeagueAddArgs) -> Result<(), FootyError> {
+    let client = build_client(cmd)?;
+
+    let query = args.query.join(" ");
+    let candidates: Vec<LeagueInfo> = match args.id {
+        Some(league_id) => client.league(league_id).await?.into_iter().collect(),
+        None => {
+            if query.trim().chars().count() < MIN_SEARCH_LENGTH {
+                return Err(FootyError::Config(format!("Search for at least {} characters of a league or country name", MIN_SEARCH_LENGTH)));
+            }
+            client.search_leagues(query.trim()).await?
+        },
+    };
+
+    if candidates.is_empty() {
+        return Err(match args.id {
+            Some(league_id) => FootyError::Api(format!("No league with ID {} found", league_id)),
+            None => FootyError::Api(format!("No league matching '{}' found. Try a different name or country", query)),
+        });
+    }
+
+    // there's a "Premier League" in dozens of countries, so let the user pick
+    let league = match prompt_choice(&candidates, "league", "Add it by ID with --id", describe_candidate)? {
+        Some(league) => league,
+        None => return Ok(()),
+    };
+
+    let mut preferred = cmd.settings.preferred_leagues.clone();
+    let mut live = cmd.settings.full_leagues.clone();
+    let added_preferred = push_unique(&mut preferred, league.league.id);
+    let added_live = push_unique(&mut live, league.league.id);
+    if !added_preferred && !added_live {
+        println!("You already follow {}", league.league.name);
+        return Ok(());
+    }
+
+    save(cmd, preferred, live)?;
+    println!("Added {} ({}, ID {}) to your leagues", league.league.name.bold(), league.country.name, league.league.id);
+    Ok(())
+}
+
+async fn remove_league(cmd: &Command, league: &str) -> Result<(), FootyError> {
+    let league_id = match league.trim().parse::<u64>() {
+        Ok(league_id) => league_id,
+        Err(_) => find_followed_by_name(cmd, league).await?,
+    };
+
+    let mut preferred = cmd.settings.preferred_leagues.clone();
+    let mut live = cmd.settings.full_leagues.clone();
+    let followed = preferred.len() + live.len();
+    preferred.retain(|id| *id != league_id);
+    live.retain(|id| *id != league_id);
+
+    if preferred.len() + live.len() == followed {
+        return Err(FootyError::Config(format!("League {} isn't one of your leagues. Run `footy leagues list` to see them", league)));
+    }
+
+    save(cmd, preferred, live)?;
+    println!("Removed league {} from your leagues", league);
+    Ok(())
+}
+
+fn move_league(cmd: &Command, args: &LeagueMoveArgs) -> Result<(), FootyError> {
+    let mut preferred = cmd.settings.preferred_leagues.clone();
+    let position = move_to(&mut preferred, args.league, args.position as usize)?;
+
+    save(cmd, preferred, cmd.settings.full_leagues.clone())?;
+    println!("Moved league {} to position {}", args.league, position);
+    Ok(())
+}
+
+// Preferred leagues first, in order, then any live-only ones
+fn followed_ids(settings: &Settings) -> Vec<u64> {
+    let mut ids = settings.preferred_leagues.clone();
+    for league_id in settings.full_leagues.iter() {
+        push_unique(&mut ids, *league_id);
+    }
+    ids
+}
+
+async fn league_details(cmd: &Command, ids: &[u64]) -> Result<HashMap<u64, LeagueInfo>, FootyError> {
+    let client = build_client(cmd)?;
+    warn_about_quota(cmd, ids.len());
+
+    let leagues = fetch_all(ids.iter().copied(), |league_id| client.league(league_id)).await?;
+    Ok(leagues.into_iter().flatten().map(|info| (info.league.id, info)).collect())
+}
+
+async fn find_followed_by_name(cmd: &Command, name: &str) -> Result<u64, FootyError> {
+    let details = league_details(cmd, &followed_ids(&cmd.settings)).await?;
+
+    details.values()
+        .find(|info| info.league.name.eq_ignore_ascii_case(name.trim()))
+        .map(|info| info.league.id)
+        .ok_or_else(|| FootyError::Config(format!("You don't follow a league called '{}'. Run `footy leagues list` to see them", name)))
+}
+
+fn describe_candidate(info: &LeagueInfo) -> String {
+    format!("{:<30} {:<20} {:<8} ID {}", info.league.name, info.country.name, info.league.kind, info.league.id)
+}
+
+fn describe(league_id: u64, details: &HashMap<u64, LeagueInfo>) -> String {
+    match details.get(&league_id) {
+        Some(info) => format!("{} ({}) ID {}", info.league.name.bold(), info.country.name, league_id),
+        None => format!("ID {}", league_id),
+    }
+}
+
+fn save(cmd: &Command, preferred: Vec<u64>, live: Vec<u64>) -> Result<(), FootyError> {
+    let path = cmd.settings.config_path.as_ref()
+        .ok_or_else(|| FootyError::Config(String::from("Couldn't find a config directory. Pass --config <path> to choose where to save your leagues")))?;
+    config::save_leagues(path, preferred, live)
+}
+
+fn push_unique(leagues: &mut Vec<u64>, league_id: u64) -> bool {
+    if leagues.contains(&league_id) { return false; }
+    leagues.push(league_id);
+    true
+}
+
+// Positions are 1-based and clamped to the end of the list; returns where the league ended up
+fn move_to(leagues: &mut Vec<u64>, league_id: u64, position: usize) -> Result<usize, FootyError> {
+    let index = leagues.iter().position(|id| *id == league_id)
+        .ok_or_else(|| FootyError::Config(format!("League {} isn't one of your preferred leagues", league_id)))?;
+
+    let league = leagues.remove(index);
+    let new_index = position.saturating_sub(1).min(leagues.len());
+    leagues.insert(new_index, league);
+    Ok(new_index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_to_reorders_and_clamps() {
+        let mut leagues = vec![39, 135, 78, 140];
+
+        assert_eq!(move_to(&mut leagues, 140, 1).unwrap(), 1);
+        assert_eq!(leagues, vec![140, 39, 135, 78]);
+
+        assert_eq!(move_to(&mut leagues, 39, 10).unwrap(), 4);
+        assert_eq!(leagues, vec![140, 135, 78, 39]);
+
+        assert!(move_to(&mut leagues, 61, 1).is_err());
+    }
+
+    #[test]
+    fn test_followed_ids_keeps_preferred_order_without_duplicates() {
+        let settings = Settings {
+            preferred_leagues: vec![140, 39],
+            full_leagues: vec![39, 2, 140, 61],
+            ..Settings::default()
+        };
+
+        assert_eq!(followed_ids(&settings), vec![140, 39, 2, 61]);
+    }
+}
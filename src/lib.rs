@@ -1,64 +1,211 @@
-use std::{env, collections::HashMap, io, error::Error, fs::OpenOptions, process};
+use std::{collections::{HashMap, HashSet}, io::{self, BufRead, IsTerminal}, future::Future, path::Path};
 
-use csv::{ReaderBuilder, StringRecord};
+use chrono::{ Utc, Local, TimeZone, NaiveDate, Duration};
+use chrono_tz::Tz;
 
-use chrono::{ Utc, Local, TimeZone};
+use clap::Parser;
 
-use reqwest::Client;
+use futures::{stream, StreamExt, TryStreamExt};
 
 use serde::{Serialize, Deserialize};
 use serde_json::{Map, Value};
 use colored::Colorize;
 
-const BASE_URL: &str = "https://api-football-v1.p.rapidapi.com/v3/fixtures?";
-
-#[derive(Debug, PartialEq)]
+mod atomic_file;
+mod cache;
+mod cli;
+mod client;
+mod color;
+mod config;
+mod error;
+mod layout;
+mod leagues;
+mod logo_colors;
+mod quota;
+mod render;
+mod retry;
+mod status;
+mod table;
+mod team_colors;
+mod teams_file;
+mod theme;
+
+use cli::{Cli, CliCommand, TeamAddArgs};
+use client::ApiFootballClient;
+use color::ColorChoice;
+use error::check_api_errors;
+use layout::{team_code, Layout};
+use quota::QuotaTracker;
+use render::RenderContext;
+use status::FixtureStatus;
+use table::Table;
+use teams_file::TeamsFile;
+pub use cache::CacheMode;
+pub use cli::{ColorsAction, LeaguesAction, OutputFormat, TeamsAction};
+pub use config::Settings;
+pub use error::FootyError;
+
+// broken until 24/25 season starts
+const DEFAULT_SEASON: u16 = 2024;
+
+// requests in flight at once; keeps us clear of the per-minute rate limit
+const MAX_CONCURRENT_REQUESTS: usize = 6;
+
+// teams play roughly weekly, so look back a week per requested result plus slack
+const SCORES_DAYS_PER_RESULT: i64 = 7;
+
+// API-Football rejects shorter team and league searches
+const MIN_SEARCH_LENGTH: usize = 3;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum CommandType {
     Scores,
     Schedule,
     Teams,
     Live,
-    Standings
+    Standings,
+    Leagues,
+    Colors,
+    Quota,
+}
+
+impl CommandType {
+    pub fn from_name(name: &str) -> Option<CommandType> {
+        match name.trim().to_lowercase().as_str() {
+            "scores" => Some(CommandType::Scores),
+            "schedule" => Some(CommandType::Schedule),
+            "teams" => Some(CommandType::Teams),
+            "live" => Some(CommandType::Live),
+            "standings" => Some(CommandType::Standings),
+            "leagues" => Some(CommandType::Leagues),
+            "quota" => Some(CommandType::Quota),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Command {
-    pub command_type: CommandType
+    pub command_type: CommandType,
+    pub date: Option<NaiveDate>,
+    pub leagues: Vec<u64>,
+    pub teams: Vec<String>,
+    pub last: u8,
+    pub season: Option<u16>,
+    pub half_time: bool,
+    pub format: OutputFormat,
+    pub layout: Layout,
+    pub color: ColorChoice,
+    pub cache_mode: CacheMode,
+    pub leagues_action: Option<LeaguesAction>,
+    pub teams_action: Option<TeamsAction>,
+    pub colors_action: Option<ColorsAction>,
+    pub settings: Settings,
 }
 
 impl Command {
+    pub fn new(command_type: CommandType) -> Command {
+        Command {
+            command_type,
+            date: None,
+            leagues: vec![],
+            teams: vec![],
+            last: 2,
+            season: None,
+            half_time: false,
+            format: OutputFormat::Table,
+            layout: Layout::Auto,
+            color: ColorChoice::Auto,
+            cache_mode: CacheMode::Normal,
+            leagues_action: None,
+            teams_action: None,
+            colors_action: None,
+            settings: Settings::default(),
+        }
+    }
+
     pub fn build(
-        mut args: impl Iterator<Item = String>,
-    ) -> Result<Command, &'static str> {
-        args.next();
-
-        // matching input to a command type
-        let command_type = match args.next() {
-            Some(arg) => match arg.as_ref() {
-                "scores" => CommandType::Scores,
-                "schedule" => CommandType::Schedule,
-                "teams" => CommandType::Teams,
-                "live" => CommandType::Live,
-                "standings" => CommandType::Standings,
-                _ => return Err("Invalid command type")
+        args: impl Iterator<Item = String>,
+    ) -> Result<Command, FootyError> {
+        let cli = Cli::try_parse_from(args).map_err(FootyError::Usage)?;
+        let settings = config::load_settings(cli.config.as_deref())?;
+        Ok(Command::from_cli(cli, settings))
+    }
+
+    // Flags on top of already loaded settings; kept apart from `build` so it can be tested
+    // without reading the user's config file or environment
+    fn from_cli(cli: Cli, settings: Settings) -> Command {
+        // no subcommand falls back to the default command from settings
+        let mut command = match cli.command {
+            Some(CliCommand::Scores(args)) => Command {
+                teams: args.teams,
+                last: args.last,
+                season: args.season,
+                half_time: args.half_time,
+                ..Command::new(CommandType::Scores)
+            },
+            Some(CliCommand::Schedule(args)) => Command {
+                date: args.date,
+                leagues: args.leagues,
+                season: args.season,
+                ..Command::new(CommandType::Schedule)
+            },
+            Some(CliCommand::Teams(args)) => Command {
+                teams_action: args.action,
+                ..Command::new(CommandType::Teams)
             },
-            None => return Err("Didn't enter any command"),
+            Some(CliCommand::Live(args)) => Command {
+                leagues: args.leagues,
+                half_time: args.half_time,
+                ..Command::new(CommandType::Live)
+            },
+            Some(CliCommand::Standings(args)) => Command {
+                leagues: args.leagues,
+                season: args.season,
+                ..Command::new(CommandType::Standings)
+            },
+            Some(CliCommand::Leagues(args)) => Command {
+                leagues_action: args.action,
+                ..Command::new(CommandType::Leagues)
+            },
+            Some(CliCommand::Colors(args)) => Command {
+                colors_action: Some(args.action),
+                ..Command::new(CommandType::Colors)
+            },
+            Some(CliCommand::Quota) => Command::new(CommandType::Quota),
+            None => Command::new(settings.default),
+        };
+        command.format = cli.format.unwrap_or(settings.format);
+        command.layout = cli.layout.unwrap_or(settings.layout);
+        command.color = cli.color;
+        command.cache_mode = match (cli.refresh, cli.offline) {
+            (true, _) => CacheMode::Refresh,
+            (_, true) => CacheMode::Offline,
+            _ => CacheMode::Normal,
         };
+        command.settings = settings;
 
-        Ok(Command {
-            command_type,
-        })
+        command
+    }
+
+    // When stdout is JSON, nothing else (banner, notes) may go there
+    pub fn prints_json(&self) -> bool {
+        self.format == OutputFormat::Json || matches!(&self.teams_action, Some(TeamsAction::List(args)) if args.json)
     }
-}
 
-pub struct Settings {
-    pub teams: HashMap<String, u64>,
-    pub preferred_leagues: Vec<u64>,
-    pub full_leagues: Vec<u64>,
-    pub default: CommandType,
+    fn season(&self) -> u16 {
+        self.season.unwrap_or(DEFAULT_SEASON)
+    }
+
+    // explicit --league flags win over the configured list
+    fn leagues_or(&self, configured: &[u64]) -> Vec<u64> {
+        if self.leagues.is_empty() { configured.to_vec() } else { self.leagues.clone() }
+    }
 }
 
 // Serde structs
+// Anything the API can send as null is an Option. Unknown fields are ignored on purpose
+// (no deny_unknown_fields) so fields added to the API don't break parsing.
 #[derive(Serialize, Deserialize, Debug)]
 struct Fixture {
     fixture: FixtureData,
@@ -71,16 +218,17 @@ struct Fixture {
 #[derive(Serialize, Deserialize, Debug)]
 struct FixtureData {
     id: u64,
-    referee: String,
+    referee: Option<String>,
     timezone: String,
     date: String,
     timestamp: i64,
+    #[serde(default)]
     periods: Periods,
     venue: Option<Venue>,
     status: Status,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 struct Periods {
     first: Option<u64>,
     second: Option<u64>,
@@ -89,14 +237,14 @@ struct Periods {
 #[derive(Serialize, Deserialize, Debug)]
 struct Venue {
     id: Option<u64>,
-    name: String,
-    city: String,
+    name: Option<String>,
+    city: Option<String>,
 }
 
 impl Clone for Venue {
     fn clone(&self) -> Self {
         Venue {
-            id: self.id.clone(),
+            id: self.id,
             name: self.name.clone(),
             city: self.city.clone(),
         }
@@ -106,7 +254,7 @@ impl Clone for Venue {
 #[derive(Serialize, Deserialize, Debug)]
 struct Status {
     long: String,
-    short: String,
+    short: FixtureStatus,
     elapsed: Option<u64>,
 }
 
@@ -115,7 +263,7 @@ struct LeagueData {
     id: u64,
     name: String,
     country: String,
-    logo: String,
+    logo: Option<String>,
     flag: Option<String>,
     season: u16,
     round: Option<String>,
@@ -131,19 +279,26 @@ struct TeamsData {
 struct Team {
     id: u64,
     name: String,
-    logo: String,
+    logo: Option<String>,
     winner: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct TeamResponse {
-    response: Vec<TeamInfo>,
+struct TeamInfo {
+    team: TeamDetails,
+    venue: Option<Venue>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct TeamInfo {
-    team: TeamCSVRecord,
-    venue: Venue,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TeamDetails {
+    id: u64,
+    name: String,
+    code: Option<String>,
+    country: Option<String>,
+    founded: Option<u16>,
+    #[serde(default)]
+    national: bool,
+    logo: Option<String>,
 }
 
 impl Clone for TeamInfo {
@@ -161,7 +316,8 @@ struct GoalsData {
     away: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
 struct ScoreData {
     halftime: HalftimeScore,
     fulltime: FulltimeScore,
@@ -169,25 +325,25 @@ struct ScoreData {
     penalty: PenaltyScore,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 struct HalftimeScore {
     home: Option<u64>,
     away: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 struct FulltimeScore {
     home: Option<u64>,
     away: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 struct ExtraTimeScore {
     home: Option<u64>,
     away: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 struct PenaltyScore {
     home: Option<u64>,
     away: Option<u64>,
@@ -203,22 +359,7 @@ impl Clone for TeamCSVRecord {
     fn clone(&self) -> Self {
         TeamCSVRecord {
             name: self.name.clone(),
-            id: self.id.clone(),
-        }
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
-struct RGBCSVRecord {
-    id: u64,
-    rgb: String,
-}
-
-impl Clone for RGBCSVRecord {
-    fn clone(&self) -> Self {
-        RGBCSVRecord {
-            id: self.id.clone(),
-            rgb: self.rgb.clone(),
+            id: self.id,
         }
     }
 }
@@ -252,254 +393,390 @@ struct TeamStanding {
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Stats {
-    draw: i32,
+    draw: Option<i32>,
     goals: GoalStats,
-    lose: i32,
-    played: i32,
-    win: i32,
+    lose: Option<i32>,
+    played: Option<i32>,
+    win: Option<i32>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct GoalStats {
-    against: i32,
+    against: Option<i32>,
     #[serde (rename="for")]
-    for_: i32,
-}
-
-pub async fn run(cmd: Command) {
-
-    let result = match_cmd_and_call(&cmd).await;
-
-    match result {
-        Ok(response_body) => {
-            if check_if_not_fixtures_trait_type(&cmd) { return; }
-            match parse_fixtures(response_body).await {
-                Ok(fixture_responses) => {
-                    if fixture_responses.iter().len() == 0 { println!("No fixtures :("); return; }
-                    for fixture_list in fixture_responses.iter() {
-                        if cmd.command_type == CommandType::Schedule && !fixture_list.is_empty() { println!("\n{}", fixture_list[0].league.name.clone()); }
-                        for fixture in fixture_list.iter() {
-                            print_based_on_command(fixture, &cmd);
-                        }
-                    }
-                }
-                Err(err) => {
-                    eprintln!("Error parsing fixtures: {}", err);
-                }
-            }
-        }
-        Err(err) => {
-            eprintln!("Error from the API: {}", err);
-        }
+    for_: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct LeagueInfo {
+    league: LeagueSummary,
+    country: Country,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct LeagueSummary {
+    id: u64,
+    name: String,
+    #[serde (rename="type")]
+    kind: String,
+    logo: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct Country {
+    name: String,
+    code: Option<String>,
+    flag: Option<String>,
+}
+
+pub async fn run(cmd: Command) -> Result<(), FootyError> {
+
+    let ctx = RenderContext::new(&cmd);
+
+    let fixture_responses = match_cmd_and_call(&cmd, &ctx).await
+        .map_err(|err| err.with_key_source(&cmd.settings.api.key_source))?;
+
+    if check_if_not_fixtures_trait_type(&cmd) { return Ok(()); }
+    if cmd.format == OutputFormat::Json {
+        print_json(&fixture_responses);
+        return Ok(());
+    }
+    if fixture_responses.iter().len() == 0 { println!("No fixtures :("); return Ok(()); }
+
+    let table = fixtures_table(&fixture_responses, cmd.command_type, &ctx);
+    if !table.is_empty() { println!("{}", table.render()); }
+
+    Ok(())
+}
+
+// One table for every league so the columns line up all the way down
+fn fixtures_table(fixture_responses: &[Vec<Fixture>], command_type: CommandType, ctx: &RenderContext) -> Table {
+    let mut table = Table::new();
+    // scores put the away side first, so say which is which
+    if command_type == CommandType::Scores && fixture_responses.iter().any(|fixture_list| !fixture_list.is_empty()) {
+        table.row(vec![String::from("Away"), String::from("Home")]);
     }
 
+    for fixture_list in fixture_responses.iter() {
+        if command_type == CommandType::Schedule && !fixture_list.is_empty() { table.line(format!("\n{}", fixture_list[0].league.name.clone())); }
+        for fixture in fixture_list.iter() {
+            add_fixture_row(&mut table, fixture, command_type, ctx);
+        }
+    }
+    table
 }
 
 // Top-level command matching
-async fn match_cmd_and_call(cmd: &Command) -> Result<Vec<String>, String> {
+async fn match_cmd_and_call(cmd: &Command, ctx: &RenderContext) -> Result<Vec<Vec<Fixture>>, FootyError> {
     match cmd.command_type {
-        CommandType::Schedule => get_schedule().await.map_err(|err| err.to_string()),
-        CommandType::Scores => get_teams_fixtures().await.map_err(|err| err.to_string()),
+        CommandType::Schedule => get_schedule(cmd, ctx).await,
+        CommandType::Scores => get_teams_fixtures(cmd).await,
         CommandType::Teams => {
-            prompt_teams_edit().await;
+            match &cmd.teams_action {
+                Some(TeamsAction::Add(args)) => add_team(cmd, args).await?,
+                Some(TeamsAction::Remove(args)) => remove_team(&cmd.settings, args.team.join(" "))?,
+                Some(TeamsAction::List(args)) => list_teams(cmd, ctx, args.json)?,
+                Some(TeamsAction::Import(args)) => import_teams(&cmd.settings, &args.file)?,
+                None => prompt_teams_edit(cmd, ctx).await?,
+            }
+            Ok(vec![])
+        },
+        CommandType::Live => get_live_fixtures(cmd).await,
+        CommandType::Standings => {
+            get_standings_for_base_leagues(cmd, ctx).await?;
+            Ok(vec![])
+        },
+        CommandType::Leagues => {
+            leagues::edit_leagues(cmd).await?;
+            Ok(vec![])
+        },
+        CommandType::Colors => {
+            match &cmd.colors_action {
+                Some(ColorsAction::Migrate(args)) => migrate_colors(&cmd.settings, &args.from, args.to.as_deref())?,
+                Some(ColorsAction::Generate(args)) => logo_colors::generate(cmd, ctx, args).await?,
+                None => {},
+            }
+            Ok(vec![])
+        },
+        CommandType::Quota => {
+            print_quota(cmd, ctx);
             Ok(vec![])
         },
-        CommandType::Live => get_live_fixtures().await.map_err(|err| err.to_string()),
-        CommandType::Standings => get_standings_for_base_leagues().await.map_err( |err| err.to_string()),
     }
 }
 
 // Football-API calling methods
-async fn get_schedule() -> Result<Vec<String>, reqwest::Error> {
+fn build_client(cmd: &Command) -> Result<ApiFootballClient, FootyError> {
+    Ok(ApiFootballClient::from_settings(&cmd.settings.api)?.with_cache_mode(cmd.cache_mode))
+}
+
+// Runs one request per item with bounded concurrency, keeping results in input order
+async fn fetch_all<I, T, F, Fut>(items: I, fetch: F) -> Result<Vec<T>, FootyError>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> Fut,
+    Fut: Future<Output = Result<T, FootyError>>,
+{
+    stream::iter(items)
+        .map(fetch)
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .try_collect()
+        .await
+}
 
-    smart_print_date();
+fn warn_about_quota(cmd: &Command, expected_calls: usize) {
+    if cmd.cache_mode != CacheMode::Offline { quota::warn_if_insufficient(expected_calls); }
+}
+
+async fn get_schedule(cmd: &Command, ctx: &RenderContext) -> Result<Vec<Vec<Fixture>>, FootyError> {
 
-    let mut res: Vec<String> = Vec::new();
+    if cmd.format == OutputFormat::Table { smart_print_date(cmd.date, ctx.timezone); }
 
-    let key = env::var("FOOTY_API_KEY").unwrap();
-    let client = Client::new();
-    let settings = load_settings();
+    let client = build_client(cmd)?;
+
+    let date = match cmd.date {
+        Some(date) => date.format("%Y-%m-%d").to_string(),
+        None => get_today_date(),
+    };
+
+    let leagues = cmd.leagues_or(&cmd.settings.preferred_leagues);
+    warn_about_quota(cmd, leagues.len());
 
     // could add a new item at end of each league query to print whitespace between leagues 
     // or print by
-    for league_id in settings.preferred_leagues {
-        let url = get_fixtures_url_by_league(league_id).await;
-        let response = client.get(url)
-            .header("X-RapidAPI-KEY", &key)
-            .header("X-RapidAPI-Host", "api-football-v1.p.rapidapi.com")
-            .send()
-            .await
-            .unwrap();
-        let body = response.text().await?;
-        res.push(body)
-    }
-    
-    Ok(res)
+    fetch_all(leagues, |league_id| client.fixtures_by_league(league_id, &date, cmd.season())).await
 }
 
-async fn get_live_fixtures() -> Result<Vec<String>, reqwest::Error> {
-    let mut res: Vec<String> = Vec::new();
-
-    let key = env::var("FOOTY_API_KEY").unwrap();
-    let client = Client::new();
-    let settings = load_settings();
+async fn get_live_fixtures(cmd: &Command) -> Result<Vec<Vec<Fixture>>, FootyError> {
+    let client = build_client(cmd)?;
 
-    let url = get_live_fixtures_url(settings).await;
-    let response = client.get(url)
-        .header("X-RapidAPI-KEY", &key)
-        .header("X-RapidAPI-Host", "api-football-v1.p.rapidapi.com")
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await?;
-    
-    if response.is_empty() { println!("No live fixtures"); }
+    warn_about_quota(cmd, 1);
+    let fixtures = client.live_fixtures(&cmd.leagues_or(&cmd.settings.full_leagues)).await?;
     
-    res.push(response);
+    if fixtures.is_empty() && cmd.format == OutputFormat::Table { println!("No live fixtures"); }
      
-    Ok(res)
-}
+    Ok(vec![fixtures])
+}
+
+// One date-range query per configured league that a followed team plays in, instead of one
+// query per team. Which leagues those are is looked up per team but cached for a week.
+// Teams short of `last` results in those leagues (cup games, international breaks) fall back
+// to a per-team lookup. With fewer teams than leagues, per-team lookups are cheaper anyway.
+async fn get_teams_fixtures(cmd: &Command) -> Result<Vec<Vec<Fixture>>, FootyError> {
+    let teams_file = TeamsFile::new(&cmd.settings.teams_file);
+    let mut teams: HashMap<String, u64> = teams_file.read()
+        .map_err(|err| FootyError::Config(format!(
+            "Couldn't read your teams file {} ({}). Check CONFIG_PATH or teams_file in config.toml",
+            teams_file.path().display(),
+            err,
+        )))?
+        .into_iter()
+        .map(|team| (team.name, team.id))
+        .collect();
+    teams.extend(cmd.settings.teams.clone());
 
-async fn get_teams_fixtures() -> Result<Vec<String>, reqwest::Error> {
-    
-    println!("Away                      Home");
-    let mut res: Vec<String> = Vec::new();
-
-    let teams_file = read_from_teams_csv();
-
-    let teams = match teams_file {
-        Ok(teams_file) => teams_file,
-        Err(_) => {
-            println!("File not found. Exiting");
-            let mut hm = HashMap::new();
-            hm.insert(String::from("Err"), 10);
-            hm
-        }
-    };
+    let client = build_client(cmd)?;
+
+    let mut team_ids: Vec<u64> = filter_teams(teams, &cmd.teams).into_values().collect();
+    team_ids.sort();
 
-    if teams.contains_key("Err") {
-        process::exit(1);
+    let mut configured: Vec<u64> = Vec::new();
+    for league_id in cmd.settings.preferred_leagues.iter().chain(&cmd.settings.full_leagues) {
+        if !configured.contains(league_id) { configured.push(*league_id); }
     }
+    let mut fixtures = if team_ids.len() < configured.len() {
+        warn_about_quota(cmd, team_ids.len());
+        vec![]
+    } else {
+        // at most: every team's league lookup, every configured league, and every team falling back
+        warn_about_quota(cmd, team_ids.len() + configured.len() + team_ids.len());
+        league_results(cmd, &client, &team_ids, &configured).await?
+    };
 
-    let key = env::var("FOOTY_API_KEY").unwrap();
-    let client = Client::new();
+    let missing = short_of_results(&fixtures, &team_ids, cmd.last as usize);
+    let fallback = fetch_all(missing, |team_id| client.team_fixtures(team_id, cmd.season(), cmd.last)).await?;
+    fixtures.extend(fallback.into_iter().flatten());
 
-    for (_team, team_id) in teams {
-        let url = get_team_url(team_id).await;
-        let response = client.get(url)
-            .header("X-RapidAPI-KEY", &key)
-            .header("X-RapidAPI-Host", "api-football-v1.p.rapidapi.com")
-            .send()
-            .await
-            .unwrap()
-            .text()
-            .await?;
-        res.push(response);
-    }
+    let mut results = collect_team_results(fixtures, &team_ids, cmd.last as usize);
+    results.retain(|team_results| !team_results.is_empty());
 
-    Ok(res)
+    Ok(results)
 }
 
-async fn try_get_team_id(team: String) -> Result<TeamInfo, Box<dyn Error>> {
-    let key = env::var("FOOTY_API_KEY").unwrap();
-    let url = format!("{}?name={}", "https://api-football-v1.p.rapidapi.com/v3/teams", team);
-    let client = Client::new();
+// Finished fixtures from the last few weeks of every configured league the teams play in
+async fn league_results(cmd: &Command, client: &ApiFootballClient, team_ids: &[u64], configured: &[u64]) -> Result<Vec<Fixture>, FootyError> {
+    let team_leagues = fetch_all(team_ids.iter().copied(), |team_id| client.team_leagues(team_id, cmd.season())).await?;
+    let leagues = leagues_played_in(configured, &team_leagues);
 
-    let response = client.get(url)
-        .header("X-RapidAPI-KEY", &key)
-        .header("X-RapidAPI-Host", "api-football-v1.p.rapidapi.com")
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await?;
+    let today = Utc::now().date_naive();
+    let from = (today - Duration::days(SCORES_DAYS_PER_RESULT * (cmd.last as i64 + 1))).format("%Y-%m-%d").to_string();
+    let to = today.format("%Y-%m-%d").to_string();
 
-    let team_response: TeamResponse = serde_json::from_str(&response)?;
+    let league_fixtures = fetch_all(leagues, |league_id| client.fixtures_in_range(league_id, cmd.season(), &from, &to)).await?;
+    Ok(league_fixtures.into_iter().flatten().filter(is_finished).collect())
+}
 
-    match team_response.response.get(0).cloned() {
-        Some(data) => Ok(data),
-        None => Err("Not a valid team. Try again!".to_string().into()),
-    }
+// Teams with fewer than `last` finished fixtures among `fixtures`
+fn short_of_results(fixtures: &[Fixture], team_ids: &[u64], last: usize) -> Vec<u64> {
+    team_ids.iter()
+        .filter(|team_id| {
+            let played: HashSet<u64> = fixtures.iter()
+                .filter(|fixture| is_finished(fixture) && fixture_involves(fixture, **team_id))
+                .map(|fixture| fixture.fixture.id)
+                .collect();
+            played.len() < last
+        })
+        .copied()
+        .collect()
 }
 
-async fn get_standings_for_base_leagues() -> Result<Vec<String>,  Box<dyn Error>> {
-    let key = env::var("FOOTY_API_KEY").unwrap();
-    let client = Client::new();
+// The configured leagues, in order, that at least one of the teams plays in
+fn leagues_played_in(configured: &[u64], team_leagues: &[Vec<LeagueInfo>]) -> Vec<u64> {
+    configured.iter()
+        .filter(|league_id| team_leagues.iter().flatten().any(|info| info.league.id == **league_id))
+        .copied()
+        .collect()
+}
 
-    let settings = load_settings();
+fn is_finished(fixture: &Fixture) -> bool {
+    fixture.fixture.status.short.is_finished()
+}
 
-    let mut res: Vec<String> = Vec::new();
+fn fixture_involves(fixture: &Fixture, team_id: u64) -> bool {
+    fixture.teams.home.id == team_id || fixture.teams.away.id == team_id
+}
 
-    for league_id in settings.preferred_leagues {
-        let url = format!("{}?league={}&season=2024", "https://api-football-v1.p.rapidapi.com/v3/standings", league_id);
-        let response = client.get(url)
-        .header("X-RapidAPI-KEY", &key)
-        .header("X-RapidAPI-Host", "api-football-v1.p.rapidapi.com")
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await?;
+// Latest `last` finished fixtures per team, newest first; a fixture between two
+// followed teams is only listed under the first of them
+fn collect_team_results(mut fixtures: Vec<Fixture>, team_ids: &[u64], last: usize) -> Vec<Vec<Fixture>> {
+    // a match between two followed teams can come back from both of their lookups
+    let mut fixture_ids: HashSet<u64> = HashSet::new();
+    fixtures.retain(|fixture| is_finished(fixture) && fixture_ids.insert(fixture.fixture.id));
+    fixtures.sort_by_key(|fixture| std::cmp::Reverse(fixture.fixture.timestamp));
 
-        res.push(response);
+    let mut seen: HashSet<usize> = HashSet::new();
+    let mut picked: Vec<Vec<usize>> = Vec::new();
+    for team_id in team_ids {
+        let team_fixtures: Vec<usize> = fixtures.iter()
+            .enumerate()
+            .filter(|(_, fixture)| fixture_involves(fixture, *team_id))
+            .take(last)
+            .map(|(index, _)| index)
+            .collect();
+        picked.push(team_fixtures.into_iter().filter(|index| seen.insert(*index)).collect());
     }
 
-    match parse_standings(&res) {
-        Ok(standings) => {
-            print_standings_by_league(standings);
-        },
-        Err(err) => {
-            println!("Error occurred: {}", err);
-        }
+    let mut fixtures: Vec<Option<Fixture>> = fixtures.into_iter().map(Some).collect();
+    picked.into_iter()
+        .map(|indexes| indexes.into_iter().filter_map(|index| fixtures[index].take()).collect())
+        .collect()
+}
+
+// "Arsenal" alone matches the Gunners, Arsenal Tula and a handful of youth and women's sides,
+// so return every candidate and let the caller choose
+async fn find_teams(cmd: &Command, name: &str, country: Option<&str>, team_id: Option<u64>) -> Result<Vec<TeamInfo>, FootyError> {
+    let client = build_client(cmd)?;
 
+    let teams = match team_id {
+        Some(team_id) => client.team(team_id).await?,
+        None => {
+            if name.trim().chars().count() < MIN_SEARCH_LENGTH {
+                return Err(FootyError::Config(format!("Search for at least {} characters of a team name", MIN_SEARCH_LENGTH)));
+            }
+            client.search_teams(name.trim()).await?
+        },
     };
+    let teams = filter_by_country(teams, country);
+
+    if teams.is_empty() {
+        return Err(match (team_id, country) {
+            (Some(team_id), _) => FootyError::Api(format!("No team with ID {} found", team_id)),
+            (None, Some(country)) => FootyError::Api(format!("No team called '{}' found in {}. Try again!", name, country)),
+            (None, None) => FootyError::Api(format!("No team called '{}' found. Try again!", name)),
+        });
+    }
+    Ok(teams)
+}
 
-    Ok(res)
+fn filter_by_country(teams: Vec<TeamInfo>, country: Option<&str>) -> Vec<TeamInfo> {
+    match country {
+        Some(country) => teams.into_iter()
+            .filter(|info| info.team.country.as_deref().is_some_and(|name| name.eq_ignore_ascii_case(country.trim())))
+            .collect(),
+        None => teams,
+    }
 }
 
-// Serde parsing
-async fn parse_fixtures(json_list: Vec<String>) -> Result<Vec<Vec<Fixture>>, Box<dyn Error>> {
+async fn get_standings_for_base_leagues(cmd: &Command, ctx: &RenderContext) -> Result<(),  FootyError> {
+    let client = build_client(cmd)?;
 
-    // logic to step out on add: no fixtures to parse
-    if json_list.is_empty() {
-        return Ok(vec![vec![]])
-    }
+    let leagues = cmd.leagues_or(&cmd.settings.preferred_leagues);
+    warn_about_quota(cmd, leagues.len());
 
-    let mut res: Vec<Vec<Fixture>> = Vec::new();
-    for json in json_list {
-        let data: Map<String, Value> = serde_json::from_str(&json)?;
-        let response = data.get("response").ok_or("Missing 'response' field")?;
-        let league_fixture_list: Vec<Fixture> = serde_json::from_value(response.clone())?;
-        res.push(league_fixture_list);
+    let standings = fetch_all(leagues, |league_id| client.standings(league_id, cmd.season())).await?;
+
+    match cmd.format {
+        OutputFormat::Table => print_standings_by_league(standings, ctx),
+        OutputFormat::Json => print_json(&standings),
     }
 
-    Ok(res)
+    Ok(())
 }
 
-fn parse_standings(raw_response: &Vec<String>) -> Result<Vec<Vec<Vec<TeamStanding>>>, Box<dyn Error>> {
-    if raw_response.is_empty() {
-        return Ok(vec![])
-    }
+// Serde parsing
+fn get_response_field(json: &str) -> Result<Value, FootyError> {
+    let mut data: Map<String, Value> = serde_json::from_str(json)?;
+    check_api_errors(&data)?;
+    let response = data.remove("response")
+        .ok_or_else(|| FootyError::Parse(String::from("Missing 'response' field")))?;
+    Ok(response)
+}
+
+// Fixtures are parsed one at a time so a single malformed one is reported and skipped
+// instead of hiding every other fixture in the league
+fn parse_fixtures(json: &str) -> Result<Vec<Fixture>, FootyError> {
+    let response = match get_response_field(json)? {
+        Value::Array(response) => response,
+        other => return Err(FootyError::Parse(format!("Expected a list of fixtures, got {}", other))),
+    };
 
-    let mut leagues_list: Vec<Vec<Vec<TeamStanding>>> = Vec::new();
-    for json_response in raw_response {
-        let data: Map<String, Value> = serde_json::from_str(json_response)?;
-        let response: &Value = data.get("response").ok_or("Missing 'response' field")?;
-        let standings_response: StandingsResponse = serde_json::from_value(response[0].clone())?;
-        let team_standings: Vec<Vec<TeamStanding>> = standings_response.league.standings;
-        leagues_list.push(team_standings);
+    let mut league_fixture_list: Vec<Fixture> = vec![];
+    for item in response {
+        let fixture_id = item["fixture"]["id"].as_u64();
+        match serde_json::from_value(item) {
+            Ok(fixture) => league_fixture_list.push(fixture),
+            Err(err) => match fixture_id {
+                Some(fixture_id) => eprintln!("Skipping fixture {}: {}", fixture_id, err),
+                None => eprintln!("Skipping a fixture without an ID: {}", err),
+            },
+        }
     }
-    Ok(leagues_list)
+    Ok(league_fixture_list)
+}
+
+fn parse_teams(json: &str) -> Result<Vec<TeamInfo>, FootyError> {
+    let response = get_response_field(json)?;
+    let teams: Vec<TeamInfo> = serde_json::from_value(response)?;
+    Ok(teams)
+}
+
+fn parse_standings(json: &str) -> Result<Vec<Vec<TeamStanding>>, FootyError> {
+    let response = get_response_field(json)?;
+    let first = response.get(0)
+        .ok_or_else(|| FootyError::Api(String::from("No standings available for this league and season")))?;
+    let standings_response: StandingsResponse = serde_json::from_value(first.clone())?;
+    Ok(standings_response.league.standings)
+}
+
+fn parse_leagues(json: &str) -> Result<Vec<LeagueInfo>, FootyError> {
+    let response = get_response_field(json)?;
+    let leagues: Vec<LeagueInfo> = serde_json::from_value(response)?;
+    Ok(leagues)
 }
 
 // Utils Functions
 fn check_if_not_fixtures_trait_type(cmd: &Command) -> bool {
-    match cmd.command_type {
-        CommandType::Teams => true,
-        CommandType::Standings => true,
-        _ => false,
-    }
+    matches!(cmd.command_type, CommandType::Teams | CommandType::Standings | CommandType::Leagues | CommandType::Colors | CommandType::Quota)
 }
 
 fn get_today_date() -> String {
@@ -507,115 +784,181 @@ fn get_today_date() -> String {
     now.format("%Y-%m-%d").to_string()
 }
 
-fn unix_to_cst (unix_timestamp: i64) -> String {
-    let local_time = Local.timestamp_opt(unix_timestamp, 0).unwrap();
-    local_time.format("%H:%M").to_string()
+// formats in the configured timezone, or the system's when there isn't one; "-" if out of range
+fn format_timestamp(unix_timestamp: i64, format: &str, timezone: Option<Tz>) -> String {
+    let formatted = match timezone {
+        Some(tz) => tz.timestamp_opt(unix_timestamp, 0).single().map(|time| time.format(format).to_string()),
+        None => Local.timestamp_opt(unix_timestamp, 0).single().map(|time| time.format(format).to_string()),
+    };
+    formatted.unwrap_or_else(|| String::from("-"))
+}
+
+fn unix_to_cst (unix_timestamp: i64, timezone: Option<Tz>) -> String {
+    format_timestamp(unix_timestamp, "%H:%M", timezone)
 }
 
-fn unix_to_date (unix_timestamp: i64) -> String {
-    let local_time = Local.timestamp_opt(unix_timestamp, 0).unwrap();
-    local_time.format("%m-%d").to_string()
+fn unix_to_date (unix_timestamp: i64, timezone: Option<Tz>) -> String {
+    format_timestamp(unix_timestamp, "%m-%d", timezone)
 }
 
-fn smart_print_date() {
-    let date = unix_to_date(Utc::now().timestamp());
+fn smart_print_date(requested: Option<NaiveDate>, timezone: Option<Tz>) {
+    if let Some(date) = requested {
+        println!("{} Fixtures", date.format("%m-%d"));
+        return;
+    }
+
+    let date = unix_to_date(Utc::now().timestamp(), timezone);
 
-    let hour = unix_to_cst(Utc::now().timestamp());
-    let hr_int = hour[0..2].parse::<i64>().unwrap();
+    let hour = unix_to_cst(Utc::now().timestamp(), timezone);
+    let hr_int = hour.get(0..2).and_then(|hour| hour.parse::<i64>().ok()).unwrap_or(0);
     if hr_int > 18 {
-        println!("{} Fixtures", unix_to_date(Utc::now().timestamp()+40000));
+        println!("{} Fixtures", unix_to_date(Utc::now().timestamp()+40000, timezone));
     } else {
         println!("{} Fixtures", date);
     }
 }
 
-fn read_from_teams_csv() -> Result<HashMap<String, u64>, Box<dyn std::error::Error>> {
-    let mut teams_with_ids: HashMap<String, u64> = HashMap::new();
-    let path = env::var("CONFIG_PATH");
-    let path_string = path.unwrap_or("./teams.csv".to_string());
-    let mut csv = ReaderBuilder::new().has_headers(false).delimiter(b',').from_path(path_string)?;
+// keeps teams matching any filter by name (case-insensitive) or ID
+fn filter_teams(teams: HashMap<String, u64>, filters: &[String]) -> HashMap<String, u64> {
+    if filters.is_empty() { return teams; }
 
-    for res in csv.records() {
-        let row: StringRecord = res?;
-        let team_record: TeamCSVRecord = row.deserialize(None)?;
-        teams_with_ids.insert(team_record.name, team_record.id);
-    }
-    Ok(teams_with_ids)
+    teams.into_iter()
+        .filter(|(name, id)| filters.iter().any(|filter| {
+            filter.eq_ignore_ascii_case(name) || filter.trim() == id.to_string()
+        }))
+        .collect()
 }
 
-fn read_ids_and_rgb_from_csv() -> Result<HashMap<u64, String>, Box<dyn std::error::Error>> {
-
-    let mut team_ids_and_rgb: HashMap<u64, String> = HashMap::new();
+async fn add_team(cmd: &Command, args: &TeamAddArgs) -> Result<(), FootyError> {
+    let candidates = find_teams(cmd, &args.name.join(" "), args.country.as_deref(), args.id).await?;
 
-    let path = env::var("RGB_PATH");
-    let path_string = path.unwrap_or("./id_rgb.csv".to_string());
-    let mut csv = ReaderBuilder::new().has_headers(false).delimiter(b',').from_path(path_string)?;
+    let narrow = "Add it by ID with --id, or narrow the search with --country";
+    let info = match prompt_choice(&candidates, "team", narrow, describe_team)? {
+        Some(info) => info,
+        None => return Ok(()),
+    };
 
-    for res in csv.records() {
-        let row: StringRecord = res?;
-        let rgb_record: RGBCSVRecord = row.deserialize(None)?;
-        team_ids_and_rgb.insert(rgb_record.id, rgb_record.rgb);
+    let added = TeamsFile::new(&cmd.settings.teams_file)
+        .add(vec![TeamCSVRecord { name: info.team.name.clone(), id: info.team.id }])?;
+    if added.is_empty() {
+        println!("{} is already in your teams", info.team.name);
+        return Ok(());
     }
-    Ok(team_ids_and_rgb)
+    println!("Added {}", describe_team(info));
+
+    Ok(())
 }
 
-async fn add_team(team: String) -> Result<(), reqwest::Error> {
+fn describe_team(info: &TeamInfo) -> String {
+    let mut details = vec![info.team.country.clone().unwrap_or_else(|| String::from("?"))];
+    if let Some(founded) = info.team.founded { details.push(format!("founded {}", founded)); }
+    if let Some(venue) = info.venue.as_ref().and_then(|venue| venue.name.clone()) { details.push(venue); }
+    details.push(String::from(if info.team.national { "national team" } else { "club" }));
 
-    let t = team.clone();
+    format!("{} ({}) ID {}", info.team.name.bold(), details.join(", "), info.team.id)
+}
 
-    match try_get_team_id(team).await  {
-        Ok(team_struct) => {
-            let _ = add_team_to_csv(team_struct.team).unwrap();
-            println!("Added {}", t);
-        },
-        Err(_error) => {
-            println!("Not a valid team.");
-        }
+// Lists candidates and reads a number from stdin; None only when the user cancels with a
+// blank line. Without a terminal to ask, more than one match is an error, so scripts fail
+// loudly instead of adding nothing. `narrow` says how to pick one without the prompt.
+fn prompt_choice<'a, T>(candidates: &'a [T], what: &str, narrow: &str, describe: impl Fn(&T) -> String) -> Result<Option<&'a T>, FootyError> {
+    let stdin = io::stdin();
+    let input = if stdin.is_terminal() { Some(stdin.lock()) } else { None };
+    choose(candidates, what, narrow, describe, input)
+}
+
+fn choose<'a, T>(
+    candidates: &'a [T],
+    what: &str,
+    narrow: &str,
+    describe: impl Fn(&T) -> String,
+    input: Option<impl BufRead>,
+) -> Result<Option<&'a T>, FootyError> {
+    if candidates.len() == 1 { return Ok(candidates.first()); }
+
+    let listing: Vec<String> = candidates.iter().enumerate()
+        .map(|(index, candidate)| format!("{:>3}. {}", index + 1, describe(candidate)))
+        .collect();
+    let Some(mut input) = input else {
+        return Err(FootyError::Config(format!(
+            "Found {} matches:\n{}\n{}",
+            candidates.len(),
+            listing.join("\n"),
+            narrow,
+        )));
+    };
+
+    println!("Found {} matches:\n{}", candidates.len(), listing.join("\n"));
+    println!("\nEnter the number of the {} to add (blank to cancel):", what);
+
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Err(FootyError::Config(format!("No {} was picked. {}", what, narrow)));
     }
 
-    Ok(())
+    let line = line.trim();
+    if line.is_empty() {
+        println!("Cancelled, nothing was added");
+        return Ok(None);
+    }
+    match line.parse::<usize>() {
+        Ok(choice) if choice >= 1 && choice <= candidates.len() => Ok(candidates.get(choice - 1)),
+        _ => Err(FootyError::Config(format!("'{}' isn't a number from 1 to {}. {}", line, candidates.len(), narrow))),
+    }
 }
 
-fn add_team_to_csv(team: TeamCSVRecord) -> Result<(), Box<dyn std::error::Error>> {
-
-    let file = OpenOptions::new()
-    .create(true)
-    .append(true)
-    .open("./teams.csv")?;
+fn remove_team(settings: &Settings, team: String) -> Result<(), FootyError> {
+    let removed = TeamsFile::new(&settings.teams_file).remove(&team)?;
+    if removed.is_empty() {
+        return Err(FootyError::Config(format!("{} isn't in your teams. Run `footy teams list` to see them", team)));
+    }
 
-    let mut csv_writer = csv::WriterBuilder::new()
-        .has_headers(false)
-        .delimiter(b',')
-        .from_writer(file);
-    
-    csv_writer.serialize(team)?;
+    for record in removed {
+        println!("Removed {} from your teams list", record.name);
+    }
+    Ok(())
+}
 
-    csv_writer.flush()?;
+fn list_teams(cmd: &Command, ctx: &RenderContext, json: bool) -> Result<(), FootyError> {
+    let teams = TeamsFile::new(&cmd.settings.teams_file).read_or_empty()?;
 
+    if json || cmd.format == OutputFormat::Json {
+        print_json(&teams);
+    } else if teams.is_empty() {
+        println!("You don't follow any teams yet. Add one with `footy teams add <name>`");
+    } else {
+        print_all_teams(&cmd.settings, ctx)?;
+    }
     Ok(())
 }
 
-fn remove_team_from_csv(team: String) -> Result<(), Box<dyn Error>> {
-    let mut csv_reader = ReaderBuilder::new().has_headers(false).delimiter(b',').from_path("./teams.csv").unwrap();
+// Imports another file in teams.csv format (name,id), skipping teams that are already followed
+fn import_teams(settings: &Settings, path: &Path) -> Result<(), FootyError> {
+    let imported = TeamsFile::new(path).read()?;
+    let total = imported.len();
 
-    let mut records: Vec<TeamCSVRecord> = csv_reader.deserialize().collect::<Result<Vec<_>, _>>()?;
+    let added = TeamsFile::new(&settings.teams_file).add(imported)?;
 
-    records.retain(|record| record.name.to_lowercase() != team.to_lowercase());
+    println!("Imported {} teams from {} ({} already followed)", added.len(), path.display(), total - added.len());
+    Ok(())
+}
 
-    let file = OpenOptions::new().write(true).truncate(true).open("./teams.csv")?;
-    let mut csv_writer = csv::WriterBuilder::new().has_headers(false).delimiter(b',').from_writer(file);
+// Writes to the configured colours file unless told otherwise, so it's picked up straight away
+fn migrate_colors(settings: &Settings, from: &Path, to: Option<&Path>) -> Result<(), FootyError> {
+    let to = to.unwrap_or(&settings.colors.file);
+    let (written, black_and_white) = team_colors::migrate(from, to)?;
 
-    for record in records {
-        csv_writer.serialize(record)?;
+    println!("Wrote {} team colours from {} to {}", written, from.display(), to.display());
+    if black_and_white > 0 {
+        println!("{} teams had no colour and were given black and white", black_and_white);
+    }
+    if to != settings.colors.file {
+        println!("Set `file = \"{}\"` under [colors] in config.toml to use it", to.display());
     }
-        
-    csv_writer.flush()?;
-
-    println!("Removed {} from your teams list", team);
     Ok(())
 }
 
-async fn prompt_teams_edit()  {
+async fn prompt_teams_edit(cmd: &Command, ctx: &RenderContext) -> Result<(), FootyError> {
     println!("Type 'a' to add a team or 'r' to remove a team");
 
     let mut char_input = String::new();
@@ -623,257 +966,327 @@ async fn prompt_teams_edit()  {
     let stdin = io::stdin();
 
     // Read user input into the `user_input` string
-    stdin.read_line(&mut char_input)
-        .expect("Failed to read input");
+    stdin.read_line(&mut char_input)?;
 
     match char_input.trim() {
         "a" => {
-            let team = get_team_input('a');
-            let _ = add_team(team).await;
+            let team = get_team_input('a', &cmd.settings, ctx)?;
+            add_team(cmd, &TeamAddArgs { name: vec![team], country: None, id: None }).await
         }
         "r" => {
-            let team = get_team_input('r');
-            let _ = remove_team_from_csv(team);
+            let team = get_team_input('r', &cmd.settings, ctx)?;
+            remove_team(&cmd.settings, team)
         },
         &_ => {
             println!("Invalid input");
             // todo: prompt_add().await;
+            Ok(())
         }
     }
 }
 
-fn get_team_input(opt: char) -> String {
+fn get_team_input(opt: char, settings: &Settings, ctx: &RenderContext) -> Result<String, FootyError> {
     if opt == 'a' {
         println!("Enter a team to add to your list of teams: ");
     } else if opt == 'r' {
         println!("\nYour current teams: ");
-        print_all_teams();
+        print_all_teams(settings, ctx)?;
         println!("\nEnter a team to remove from your list of teams:");
     }
     
     let mut team_input = String::new();
 
     let stdin = io::stdin();
-    stdin.read_line(&mut team_input)
-        .expect("Failed to read input");
+    stdin.read_line(&mut team_input)?;
     
-    team_input.trim().to_string()
+    Ok(team_input.trim().to_string())
 }
 
 // URL Configuration Functions
-async fn get_fixtures_url_by_league(league_id: u64) -> String {
-    let date = get_today_date();
-    // broken until 24/25 season starts
-    //let season = &date[0..4];
-    let season = 2024;
-    format!("{}league={}&season={}&date={}", BASE_URL, league_id, season, date)
+fn get_fixtures_url_by_league(base_url: &str, league_id: u64, date: &str, season: u16) -> String {
+    format!("{}/fixtures?league={}&season={}&date={}", base_url, league_id, season, date)
 }
 
-async fn get_live_fixtures_url(settings: Settings) -> String {
+fn get_live_fixtures_url(base_url: &str, leagues: &[u64]) -> String {
     let mut leagues_live_field: String = String::from("");
-    for league_id in settings.full_leagues {
+    for league_id in leagues {
         let append_item = format!("{}{}", league_id, "-");
         leagues_live_field = leagues_live_field + &append_item;
     }
     leagues_live_field.pop();
-    let url = format!("{}live={}", BASE_URL, leagues_live_field);
+    let url = format!("{}/fixtures?live={}", base_url, leagues_live_field);
     url
 }
 
-async fn get_team_url(team_id: u64) -> String {
-    let url = format!("{}season=2024&team={}&last=2", BASE_URL, team_id);
+fn get_team_url(base_url: &str, team_id: u64, season: u16, last: u8) -> String {
+    let url = format!("{}/fixtures?season={}&team={}&last={}", base_url, season, team_id, last);
     url
-} 
+}
 
-// Settings functions
-fn load_settings() -> Settings {
-    let pref_leagues_vec: Vec<u64> = vec!(39, 135, 78, 140);
-    let full_leagues_vec: Vec<u64> = vec!(39, 140, 88, 78, 135, 61, 94, 253);
-    let teams_vec: HashMap<String, u64> = HashMap::new();
+fn get_fixtures_url_by_date_range(base_url: &str, league_id: u64, season: u16, from: &str, to: &str) -> String {
+    format!("{}/fixtures?league={}&season={}&from={}&to={}", base_url, league_id, season, from, to)
+}
 
-    Settings {
-        teams: teams_vec,
-        preferred_leagues: pref_leagues_vec,
-        full_leagues: full_leagues_vec,
-        default: CommandType::Schedule,
-    }
+// `search` matches partial names, where `name` only finds exact ones
+fn get_team_search_url(base_url: &str, team: &str) -> String {
+    format!("{}/teams?search={}", base_url, encode_query_value(team))
+}
+
+fn get_team_by_id_url(base_url: &str, team_id: u64) -> String {
+    format!("{}/teams?id={}", base_url, team_id)
+}
+
+fn get_standings_url(base_url: &str, league_id: u64, season: u16) -> String {
+    format!("{}/standings?league={}&season={}", base_url, league_id, season)
+}
+
+fn get_league_search_url(base_url: &str, query: &str) -> String {
+    format!("{}/leagues?search={}", base_url, encode_query_value(query))
+}
+
+// Percent-encodes everything but letters, digits and -._~ so "Brighton & Hove" can't end the
+// value early; the URL is also the cache key
+fn encode_query_value(value: &str) -> String {
+    value.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn get_league_url(base_url: &str, league_id: u64) -> String {
+    format!("{}/leagues?id={}", base_url, league_id)
+}
+
+fn get_team_leagues_url(base_url: &str, team_id: u64, season: u16) -> String {
+    format!("{}/leagues?team={}&season={}", base_url, team_id, season)
 }
 
 // Output formatting
-fn print_based_on_command(fixture: &Fixture, cmd: &Command) {
-    let colors_hashmap = read_ids_and_rgb_from_csv().unwrap();
-    match cmd.command_type {
+fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(err) => eprintln!("Error serializing output: {}", err),
+    }
+}
+
+fn add_fixture_row(table: &mut Table, fixture: &Fixture, command_type: CommandType, ctx: &RenderContext) {
+    match command_type {
         CommandType::Live => {
-            format_live_row(&colors_hashmap, &fixture);
+            table.row(format_live_row(ctx, fixture));
         },
         CommandType::Schedule => {
-            format_schedule_row(&colors_hashmap, &fixture);
+            table.row(format_schedule_row(ctx, fixture));
         },
         CommandType::Teams => {
             // Empty: printing done in functions
         },
         CommandType::Scores => {
-            format_score_row(&colors_hashmap, &fixture);
+            table.row(format_score_row(ctx, fixture));
         },
-        CommandType::Standings => {
+        CommandType::Standings | CommandType::Leagues | CommandType::Colors | CommandType::Quota => {
             // Empty: printing done in functions
         },
     }
 }
 
-fn format_live_row(colors_hashmap: &HashMap<u64, String>, fixture: &Fixture) {
-    // again, output formatting doesn't work for colorized terminal output
-    let t1_len = &fixture.teams.away.name.len();
-    let t2_len = &fixture.teams.home.name.len();
-    let t1_whitespace = 27 - t1_len;
-    let t2_whitespace = 27 - t2_len;
+fn format_live_row(ctx: &RenderContext, fixture: &Fixture) -> Vec<String> {
+    // the minute only means something while the clock is running
+    let status = &fixture.fixture.status;
+    let progress = match (&status.short, status.elapsed) {
+        (short, Some(elapsed)) if short.is_clock_running() => format!("in {}'", elapsed),
+        (FixtureStatus::HalfTime, _) => String::from("| HT"),
+        (short, _) => format!("| {}", short.label()),
+    };
 
-    print!("{}", get_text_color(&colors_hashmap, &fixture.teams.away));
-    for _i in 1..t1_whitespace { print!(" "); }
-    print!("{}", get_text_color(&colors_hashmap, &fixture.teams.home));
-    for _i in 1..t2_whitespace { print!(" "); }
-    
-    println!(
-        ": {} - {} in {}'",
-        &fixture.goals.away.unwrap().to_string().bold(),
-        &fixture.goals.home.unwrap().to_string().bold(),
-        &fixture.fixture.status.elapsed.unwrap().to_string().bold(),
-    );
-
-}
-
-fn format_score_row(colors_hashmap: &HashMap<u64, String>, fixture: &Fixture) {
-    // again, output formatting doesn't work for colorized terminal output
-    let t1_len = &fixture.teams.away.name.len();
-    let t2_len = &fixture.teams.home.name.len();
-    let t1_whitespace = 27 - t1_len;
-    let t2_whitespace = 27 - t2_len;
-
-    print!("{}", get_text_color(&colors_hashmap, &fixture.teams.away));
-    for _i in 1..t1_whitespace { print!(" "); }
-    print!("{}", get_text_color(&colors_hashmap, &fixture.teams.home));
-    for _i in 1..t2_whitespace { print!(" "); }
-    
-    println!(
-        "{} - {} on {}",
-        &fixture.goals.away.unwrap().to_string().bold(),
-        &fixture.goals.home.unwrap().to_string().bold(),
-        &fixture.fixture.date[5..10],
-    );
-
-}
-
-fn format_schedule_row(colors_hashmap: &HashMap<u64, String>, fixture: &Fixture) {
-    // again, output formatting doesn't work for colorized terminal output
-    let t1_len = &fixture.teams.away.name.len();
-    let t2_len = &fixture.teams.home.name.len();
-    let t1_whitespace = 27 - t1_len;
-    let t2_whitespace = 27 - t2_len;
-
-    print!("{}", get_text_color(&colors_hashmap, &fixture.teams.away));
-    for _i in 1..t1_whitespace { print!(" "); }
-    print!("at {}", get_text_color(&colors_hashmap, &fixture.teams.home));
-    for _i in 1..t2_whitespace { print!(" "); }
-    
-    println!(
-        "at {} {}",
-        unix_to_cst(fixture.fixture.timestamp).bold(),
-        check_if_fixture_in_progress(&fixture.fixture.status.short),
-    ); 
-}
+    let mut row = vec![
+        format_team_name(ctx, &fixture.teams.away),
+        format_team_name(ctx, &fixture.teams.home),
+        format!(": {} {}", format_score(ctx, fixture), ctx.terminal.bold(&progress)),
+    ];
+    if ctx.layout == Layout::Wide { row.extend(wide_details(fixture, true)); }
+    row
+}
+
+fn format_score_row(ctx: &RenderContext, fixture: &Fixture) -> Vec<String> {
+    // full time, extra time and penalties already show in the score itself
+    let status = &fixture.fixture.status.short;
+    let marker = match status {
+        FixtureStatus::FullTime | FixtureStatus::AfterExtraTime | FixtureStatus::AfterPenalties => String::new(),
+        _ => status_suffix(status),
+    };
 
-fn get_text_color(rgb_hash_map: &HashMap<u64, String>, team: &Team) -> String {
-    // pass in hashmap of colors read from csv and team to format
-    // use color from .get() op in true_color(r, g, b) format
-    let rgb_string = rgb_hash_map.get(&team.id);
-    let rgb_values = parse_rgb_string(rgb_string.unwrap_or(&String::from("(255, 255, 255)")));
+    // "2024-05-19T15:00:00+00:00" -> "05-19"
+    let mut row = vec![
+        format_team_name(ctx, &fixture.teams.away),
+        format_team_name(ctx, &fixture.teams.home),
+        format!("{} on {}{}", format_score(ctx, fixture), fixture.fixture.date.get(5..10).unwrap_or("-"), marker),
+    ];
+    if ctx.layout == Layout::Wide { row.extend(wide_details(fixture, true)); }
+    row
+}
+
+fn format_schedule_row(ctx: &RenderContext, fixture: &Fixture) -> Vec<String> {
+    let mut row = vec![
+        ctx.paint_team(fixture.teams.away.id, &team_label(&fixture.teams.away, ctx.layout)),
+        format!("at {}", ctx.paint_team(fixture.teams.home.id, &team_label(&fixture.teams.home, ctx.layout))),
+        format!("at {}{}", ctx.terminal.bold(&unix_to_cst(fixture.fixture.timestamp, ctx.timezone)), status_suffix(&fixture.fixture.status.short)),
+    ];
+    // the schedule already groups fixtures under their league's name
+    if ctx.layout == Layout::Wide { row.extend(wide_details(fixture, false)); }
+    row
+}
+
+// Extra columns for the wide layout; "-" wherever the API has nothing
+fn wide_details(fixture: &Fixture, with_league: bool) -> Vec<String> {
+    let or_dash = |value: Option<&String>| value.filter(|value| !value.is_empty()).cloned().unwrap_or_else(|| String::from("-"));
+    let venue = fixture.fixture.venue.as_ref();
+
+    let mut details = vec![];
+    if with_league { details.push(fixture.league.name.clone()); }
+    details.push(or_dash(fixture.league.round.as_ref()));
+    details.push(or_dash(venue.and_then(|venue| venue.name.as_ref())));
+    details.push(or_dash(fixture.fixture.referee.as_ref()));
+    details
+}
+
+// Away goals first, like the rest of the row: "2 - 2 AET", "1 - 1 (4 - 3 pens)", "1 - 0 (HT 0 - 0)"
+fn format_score(ctx: &RenderContext, fixture: &Fixture) -> String {
+    let goals = score_pair(fixture.goals.away, fixture.goals.home).unwrap_or_else(|| String::from("-"));
+    let mut parts = vec![ctx.terminal.bold(&goals)];
+
+    let score = fixture.score.as_ref();
+    match fixture.fixture.status.short {
+        FixtureStatus::AfterExtraTime => parts.push(String::from("AET")),
+        FixtureStatus::AfterPenalties | FixtureStatus::PenaltiesInProgress => {
+            if let Some(penalties) = score.and_then(|score| score_pair(score.penalty.away, score.penalty.home)) {
+                parts.push(format!("({} pens)", penalties));
+            }
+        },
+        _ => {},
+    }
 
-    team.name.truecolor(rgb_values[0], rgb_values[1], rgb_values[2]).to_string()
-}
+    if ctx.half_time {
+        if let Some(half_time_score) = score.and_then(|score| score_pair(score.halftime.away, score.halftime.home)) {
+            parts.push(format!("(HT {})", half_time_score));
+        }
+    }
 
-fn parse_rgb_string(rgb_string: &String) -> Vec<u8> {
-    // case for handling white and black teams
-    if !rgb_string.contains("(") {return vec!(255,255,255)}
+    parts.join(" ")
+}
 
-    let values: Vec<&str> = rgb_string.trim_matches(|c| c == '(' || c == ')')
-        .split(',')
-        .collect();
+fn score_pair(away: Option<u64>, home: Option<u64>) -> Option<String> {
+    Some(format!("{} - {}", away?, home?))
+}
 
-    let r: u8 = values[0].trim().parse().unwrap();
-    let g: u8 = values[1].trim().parse().unwrap();
-    let b: u8 = values[2].trim().parse().unwrap();
-    
-    vec!(r, g, b)
+// The API marks the winner (or the side currently ahead) with `winner: true`
+fn format_team_name(ctx: &RenderContext, team: &Team) -> String {
+    let name = ctx.paint_team(team.id, &team_label(team, ctx.layout));
+    if team.winner == Some(true) { ctx.terminal.bold(&name) } else { name }
 }
 
-fn print_all_teams() {
-    let colors_hashmap = read_ids_and_rgb_from_csv().unwrap();
+// Short codes in the compact layout, full names otherwise
+fn team_label(team: &Team, layout: Layout) -> String {
+    if layout == Layout::Compact { team_code(&team.name) } else { team.name.clone() }
+}
 
+fn print_all_teams(settings: &Settings, ctx: &RenderContext) -> Result<(), FootyError> {
+    let mut table = Table::new();
+    for team in TeamsFile::new(&settings.teams_file).read_or_empty()? {
+        table.row(vec![ctx.paint_team(team.id, &team.name), format!("ID {}", team.id)]);
+    }
+    if !table.is_empty() { println!("{}", table.render()); }
+    Ok(())
+}
 
-    let path = env::var("CONFIG_PATH");
-    let path_string = path.unwrap_or("./teams.csv".to_string());
-    let mut csv = ReaderBuilder::new().has_headers(false).delimiter(b',').from_path(path_string).unwrap();
+fn print_quota(cmd: &Command, ctx: &RenderContext) {
+    let state = QuotaTracker::default_location()
+        .map(|tracker| tracker.load())
+        .unwrap_or_default();
 
-    for res in csv.records() {
-        let row = res.unwrap();
-        let csv_row: TeamCSVRecord = row.deserialize(None).unwrap();
+    if cmd.format == OutputFormat::Json {
+        print_json(&state);
+        return;
+    }
 
-        let team = Team {
-            id: csv_row.id,
-            name: csv_row.name,
-            logo: "".to_string(),
-            winner: Some(true),
-        };
+    match state.remaining_today() {
+        Some(remaining) => println!(
+            "{} of {} requests remaining today",
+            ctx.terminal.bold(&remaining.to_string()),
+            state.limit.map(|limit| limit.to_string()).unwrap_or_else(|| String::from("?")),
+        ),
+        None => println!("No quota information from the API yet today"),
+    }
 
-        println!("{}", get_text_color(&colors_hashmap, &team));
+    let todays_calls = state.calls_on(Utc::now().timestamp());
+    if todays_calls.is_empty() { return; }
+
+    println!("\n{} calls today\nTime   Remaining  Endpoint", todays_calls.len());
+    for call in todays_calls {
+        println!(
+            "{}  {:<10} {}",
+            unix_to_cst(call.timestamp, ctx.timezone),
+            call.remaining.map(|remaining| remaining.to_string()).unwrap_or_else(|| String::from("-")),
+            call.endpoint,
+        );
     }
 }
 
-fn print_standings_by_league(league_standings: Vec<Vec<Vec<TeamStanding>>>) {
+fn print_standings_by_league(league_standings: Vec<Vec<Vec<TeamStanding>>>, ctx: &RenderContext) {
     for vec in league_standings {
         for league_standing in vec {
-            for team in league_standing {
-                format_team_row(team);
-            }
-            println!("\n");
+            println!("{}\n\n", format_standings_table(league_standing, ctx).render());
         }
         println!("=================================================\n")
     }
 }
 
-fn format_team_row(team: TeamStanding) {
-    if team.rank == 1 { println!("{} Table\nTeam                            Points         Form", team.group.unwrap_or_else(|| "".to_string())); } 
-    let rgb_csv = read_ids_and_rgb_from_csv().unwrap();
-    let colored_team_name_string = get_text_color(&rgb_csv, &team.team);
-    let len_team_name_string = team.team.name.len();
-    let whitespace = 27-len_team_name_string;
-    
-    let formatted_team_row = format!(
-        "{:<5} {:<25}", 
-        team.rank,
-        &colored_team_name_string,
-    );
-    print!("{}", formatted_team_row);
-    for _i in 0..whitespace {
-        print!(" ");
-    } 
-
-    let points_and_form = format!(
-        "{:<10} {:<10}",
-        team.points,
-        team.form.unwrap_or_else(|| String::from("na")),
-    );
-    println!("{}", &points_and_form);
-}
-
-fn check_if_fixture_in_progress(short_status: &String) -> &str {
-    if short_status == "FT" {
-        ""
-    }
-    else if short_status != "TBD" && short_status != "NS" {
-        "| In Progress"
+// Compact drops the form guide, wide adds the full record
+fn format_standings_table(league_standing: Vec<TeamStanding>, ctx: &RenderContext) -> Table {
+    let mut columns = vec!["", "Team", "Points"];
+    match ctx.layout {
+        Layout::Compact => {},
+        Layout::Wide => columns.extend(["Form", "P", "W", "D", "L", "GD"]),
+        Layout::Normal | Layout::Auto => columns.push("Form"),
+    }
+
+    let mut table = Table::new();
+    if let Some(group) = league_standing.first().map(|team| team.group.clone().unwrap_or_default()) {
+        table.line(format!("{} Table", group));
+        table.row(columns.iter().map(|column| column.to_string()).collect());
+    }
+
+    let or_dash = |value: Option<i32>| value.map(|value| value.to_string()).unwrap_or_else(|| String::from("-"));
+    for team in league_standing {
+        let mut row = vec![
+            team.rank.to_string(),
+            ctx.paint_team(team.team.id, &team_label(&team.team, ctx.layout)),
+            team.points.to_string(),
+        ];
+        if ctx.layout != Layout::Compact { row.push(team.form.unwrap_or_else(|| String::from("-"))); }
+        if ctx.layout == Layout::Wide {
+            row.extend([
+                or_dash(team.all.played),
+                or_dash(team.all.win),
+                or_dash(team.all.draw),
+                or_dash(team.all.lose),
+                team.goals_diff.to_string(),
+            ]);
+        }
+        table.row(row);
+    }
+    table
+}
+
+// Nothing for fixtures still to kick off, otherwise what's going on, e.g. " | Half Time" or
+// " | Postponed", space included so rows don't end in one.
+// A TBD fixture has a date but its kick-off time is a placeholder, so it's flagged on purpose.
+fn status_suffix(status: &FixtureStatus) -> String {
+    if *status == FixtureStatus::NotStarted {
+        String::new()
     } else {
-        ""
+        format!(" | {}", status.label())
     }
 }
 
@@ -882,16 +1295,22 @@ mod tests {
     use std::collections::HashMap;
 
     use super::*;
+    use crate::color::{ColorDepth, Terminal};
 
     #[test]
-    fn test_check_if_fixture_in_progress() {
-        let tbd = "TBD".to_string();
-        let ns = "NS".to_string();
-        let other = "other".to_string();
-
-        assert_eq!("", check_if_fixture_in_progress(&tbd));
-        assert_eq!("", check_if_fixture_in_progress(&ns));
-        assert_eq!("| In Progress", check_if_fixture_in_progress(&other));
+    fn test_status_suffix() {
+        assert_eq!("", status_suffix(&FixtureStatus::NotStarted));
+        assert_eq!(" | Time TBD", status_suffix(&FixtureStatus::TimeToBeDefined));
+        assert_eq!(" | 2nd Half", status_suffix(&FixtureStatus::from_code("2H")));
+        assert_eq!(" | Postponed", status_suffix(&FixtureStatus::from_code("PST")));
+        assert_eq!(" | After Penalties", status_suffix(&FixtureStatus::from_code("PEN")));
+        assert_eq!(" | XYZ", status_suffix(&FixtureStatus::from_code("XYZ")));
+
+        // the kick-off time of a TBD fixture is only a placeholder
+        let mut ctx = RenderContext::plain(Layout::Normal);
+        ctx.timezone = Some(chrono_tz::UTC);
+        assert_eq!(format_schedule_row(&ctx, &test_fixture(1, 40, 49, 0, "TBD"))[2], "at 00:00 | Time TBD");
+        assert_eq!(format_schedule_row(&ctx, &test_fixture(1, 40, 49, 0, "NS"))[2], "at 00:00");
     }
 
     #[test]
@@ -900,7 +1319,8 @@ mod tests {
         tester.insert(String::from("Liverpool"), 40);
         tester.insert(String::from("AC Milan"), 907);
 
-        let res = read_from_teams_csv();
+        let res = TeamsFile::new(Path::new("./teams.csv")).read()
+            .map(|teams| teams.into_iter().map(|team| (team.name, team.id)).collect::<HashMap<String, u64>>());
 
         match &res {
             Ok(res) => {
@@ -917,78 +1337,389 @@ mod tests {
     #[test]
     fn remove_team_test() {
 
-        let path_string = "./teams.csv";
-        
+        // work on a copy so the test never rewrites the real teams.csv
+        let path = std::env::temp_dir().join(format!("footy-remove-team-test-{}.csv", std::process::id()));
+        std::fs::copy("./teams.csv", &path).unwrap();
+        let teams_file = TeamsFile::new(&path);
+
         // add team (to ensure in Vec), check length after collecting records into vec
         let team_to_add = TeamCSVRecord {
             name: String::from("Team"),
             id: 1,
         };
 
-        let _ = add_team_to_csv(team_to_add);
-
-        let mut csv1 = ReaderBuilder::new().has_headers(false).delimiter(b',').from_path(path_string).unwrap();
-        let records1: Vec<TeamCSVRecord> = csv1.deserialize().collect::<Result<Vec<_>, _>>().unwrap();
-        let original_length = records1.len();
+        let _ = teams_file.add(vec![team_to_add]);
 
-        let _ = remove_team_from_csv(String::from("Team"));
+        let original_length = teams_file.read().unwrap().len();
 
+        let _ = teams_file.remove("Team");
 
         // collect again, compare lengths
         // assert they are different by one
-        let mut csv2 =  ReaderBuilder::new().has_headers(false).delimiter(b',').from_path(path_string).unwrap();
-        let records2: Vec<TeamCSVRecord> = csv2.deserialize().collect::<Result<Vec<_>, _>>().unwrap();
-        let new_length = records2.len();
+        let new_length = teams_file.read().unwrap().len();
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("csv.lock"));
 
         assert_eq!(original_length-1, new_length);
 
     }
 
-    #[test]
-    fn test_read_rgb_csv() {
-        let path_string = "./id_rgb.csv";
-        
-        let id_and_rgb = RGBCSVRecord {
-            id: 50,
-            rgb: String::from("(0, 35, 89)"),
-        };
-
-        let mut csv1 = ReaderBuilder::new().has_headers(false).delimiter(b',').from_path(path_string).unwrap();
-        let records1: Vec<RGBCSVRecord> = csv1.deserialize().collect::<Result<Vec<_>, _>>().unwrap();
-        
-        assert_eq!(id_and_rgb, records1[0]);
-    }
-
     #[test]
     fn test_check_if_teams_command() {
-        let cmd: Command = Command {
-            command_type: CommandType::Teams,
-        };
+        let cmd: Command = Command::new(CommandType::Teams);
         let check = check_if_not_fixtures_trait_type(&cmd);
 
-        assert_eq!(true, check);
+        assert!(check);
     }
     
     #[test]
     fn test_check_if_standings_command() {
-        let cmd: Command = Command {
-            command_type: CommandType::Standings,
-        };
+        let cmd: Command = Command::new(CommandType::Standings);
         let check = check_if_not_fixtures_trait_type(&cmd);
 
-        assert_eq!(true, check);
+        assert!(check);
     }
     
+    // Parses flags against default settings, whatever config and FOOTY_* variables this machine has
+    fn parse(args: &[&str]) -> Result<Command, FootyError> {
+        let cli = Cli::try_parse_from(args).map_err(FootyError::Usage)?;
+        Ok(Command::from_cli(cli, Settings::default()))
+    }
+
+    #[test]
+    fn test_build_defaults_to_settings_command() {
+        let cmd = parse(&["footy"]).unwrap();
+
+        assert_eq!(cmd.command_type, Settings::default().default);
+        assert_eq!(cmd.format, OutputFormat::Table);
+    }
+
+    #[test]
+    fn test_build_parses_subcommand_flags() {
+        let args = ["footy", "--format", "json", "schedule", "--date", "2024-08-17", "-l", "39,140", "--season", "2023", "--layout", "wide"];
+        let cmd = parse(&args).unwrap();
+
+        assert_eq!(cmd.command_type, CommandType::Schedule);
+        assert_eq!(cmd.date, NaiveDate::from_ymd_opt(2024, 8, 17));
+        assert_eq!(cmd.leagues, vec![39, 140]);
+        assert_eq!(cmd.season(), 2023);
+        assert_eq!(cmd.format, OutputFormat::Json);
+        assert_eq!(cmd.layout, Layout::Wide);
+        assert_eq!(cmd.color, ColorChoice::Auto);
+    }
+
+    #[test]
+    fn test_build_parses_leagues_actions() {
+        let cmd = parse(&["footy", "leagues", "add", "premier", "league"]).unwrap();
+        assert_eq!(cmd.command_type, CommandType::Leagues);
+        assert!(matches!(cmd.leagues_action, Some(LeaguesAction::Add(args)) if args.query == ["premier", "league"]));
+
+        let cmd = parse(&["footy", "leagues", "move", "140", "1"]).unwrap();
+        assert!(matches!(cmd.leagues_action, Some(LeaguesAction::Move(args)) if args.league == 140 && args.position == 1));
+
+        assert_eq!(parse(&["footy", "leagues"]).unwrap().leagues_action, None);
+        assert!(parse(&["footy", "leagues", "add"]).is_err());
+        assert!(parse(&["footy", "leagues", "move", "140", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_build_parses_colors_generate() {
+        let cmd = parse(&["footy", "colors", "generate", "40.png", "50.png", "--to", "colors.csv"]).unwrap();
+        assert!(matches!(&cmd.colors_action, Some(ColorsAction::Generate(args))
+            if args.logos.len() == 2 && !args.followed && args.to.as_deref() == Some(Path::new("colors.csv"))));
+
+        let cmd = parse(&["footy", "colors", "generate", "--followed"]).unwrap();
+        assert!(matches!(&cmd.colors_action, Some(ColorsAction::Generate(args)) if args.logos.is_empty() && args.followed));
+
+        assert!(parse(&["footy", "colors", "generate"]).is_err());
+    }
+
+    #[test]
+    fn test_team_search_candidates_filter_by_country() {
+        let json = r#"{"errors": [], "response": [
+            {"team": {"id": 42, "name": "Arsenal", "code": "ARS", "country": "England", "founded": 1886, "national": false, "logo": null},
+             "venue": {"id": 494, "name": "Emirates Stadium", "city": "London"}},
+            {"team": {"id": 1080, "name": "Arsenal Tula", "code": null, "country": "Russia", "founded": 1946, "national": false, "logo": null},
+             "venue": {"id": null, "name": null, "city": null}}
+        ]}"#;
+
+        let teams = parse_teams(json).unwrap();
+        assert_eq!(teams.len(), 2);
+        assert!(describe_team(&teams[0]).contains("England, founded 1886, Emirates Stadium, club"));
+
+        let english = filter_by_country(teams, Some("england"));
+        assert_eq!(english.len(), 1);
+        assert_eq!(english[0].team.id, 42);
+
+        let args = ["footy", "teams", "add", "Arsenal", "--country", "England"];
+        let cmd = parse(&args).unwrap();
+        assert!(matches!(cmd.teams_action, Some(TeamsAction::Add(args)) if args.country.as_deref() == Some("England")));
+    }
+
+    #[test]
+    fn test_choose_fails_instead_of_adding_nothing() {
+        let candidates = ["Arsenal", "Arsenal Tula"];
+        let pick = |input: Option<&str>| {
+            choose(&candidates, "team", "Use --id", |name| name.to_string(), input.map(io::Cursor::new)).map(|choice| choice.copied())
+        };
+
+        assert_eq!(pick(Some("2\n")).unwrap(), Some("Arsenal Tula"));
+        assert_eq!(pick(Some("\n")).unwrap(), None);
+        assert!(matches!(pick(Some("3\n")), Err(FootyError::Config(message)) if message.ends_with("Use --id")));
+        assert!(matches!(pick(Some("")), Err(FootyError::Config(message)) if message.ends_with("Use --id")));
+        // no terminal to ask
+        assert!(matches!(pick(None), Err(FootyError::Config(message)) if message.contains("2. Arsenal Tula") && message.ends_with("Use --id")));
+        assert_eq!(choose(&candidates[..1], "team", "", |name| name.to_string(), None::<io::Cursor<&str>>).unwrap(), Some(&"Arsenal"));
+    }
+
+    #[test]
+    fn test_search_urls_encode_the_search_term() {
+        assert_eq!(get_team_search_url("https://host/v3", "Brighton & Hove"), "https://host/v3/teams?search=Brighton%20%26%20Hove");
+        assert_eq!(get_team_search_url("https://host/v3", "Atlético"), "https://host/v3/teams?search=Atl%C3%A9tico");
+        assert_eq!(get_league_search_url("https://host/v3", "Serie A#1+2"), "https://host/v3/leagues?search=Serie%20A%231%2B2");
+    }
+
+    #[test]
+    fn test_build_parses_teams_actions() {
+        let cmd = parse(&["footy", "teams", "remove", "AC", "Milan"]).unwrap();
+        assert!(matches!(cmd.teams_action, Some(TeamsAction::Remove(args)) if args.team.join(" ") == "AC Milan"));
+
+        let cmd = parse(&["footy", "teams", "list", "--json"]).unwrap();
+        assert_eq!(cmd.teams_action, Some(TeamsAction::List(cli::TeamListArgs { json: true })));
+
+        let cmd = parse(&["footy", "teams", "import", "dotfiles/teams.csv"]).unwrap();
+        assert!(matches!(cmd.teams_action, Some(TeamsAction::Import(args)) if args.file == Path::new("dotfiles/teams.csv")));
+
+        assert_eq!(parse(&["footy", "teams"]).unwrap().teams_action, None);
+        assert!(parse(&["footy", "teams", "remove"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_leagues() {
+        let json = r#"{"errors": [], "response": [
+            {"league": {"id": 88, "name": "Eredivisie", "type": "League", "logo": "https://media.api-sports.io/football/leagues/88.png"},
+             "country": {"name": "Netherlands", "code": "NL", "flag": null},
+             "seasons": []}
+        ]}"#;
+
+        let leagues = parse_leagues(json).unwrap();
+
+        assert_eq!(leagues.len(), 1);
+        assert_eq!(leagues[0].league.id, 88);
+        assert_eq!(leagues[0].league.kind, "League");
+        assert_eq!(leagues[0].country.name, "Netherlands");
+    }
+
+    #[test]
+    fn test_leagues_played_in_keeps_configured_order() {
+        let info = |id: u64| parse_leagues(&format!(
+            r#"{{"errors": [], "response": [{{"league": {{"id": {}, "name": "", "type": "League", "logo": null}}, "country": {{"name": "", "code": null, "flag": null}}}}]}}"#,
+            id,
+        )).unwrap().remove(0);
+
+        // Liverpool: Premier League and Champions League; Barcelona: La Liga
+        let team_leagues = vec![vec![info(39), info(2)], vec![info(140)]];
+        assert_eq!(leagues_played_in(&[140, 78, 39, 135], &team_leagues), vec![140, 39]);
+        assert!(leagues_played_in(&[78], &team_leagues).is_empty());
+    }
+
+    #[test]
+    fn test_build_rejects_unknown_command() {
+        let args = ["footy", "fixtures"];
+        assert!(parse(&args).is_err());
+    }
+
+    #[test]
+    fn test_parse_fixtures_surfaces_api_errors() {
+        let missing_key = r#"{"errors": {"token": "Error/Missing application key."}, "response": []}"#;
+        let over_quota = r#"{"errors": {"requests": "You have reached the request limit for the day"}, "response": []}"#;
+
+        assert!(matches!(parse_fixtures(missing_key), Err(FootyError::Auth(_))));
+        assert!(matches!(parse_standings(over_quota), Err(FootyError::Quota(_))));
+        assert!(matches!(parse_standings(r#"{"errors": [], "response": []}"#), Err(FootyError::Api(_))));
+        assert!(matches!(parse_fixtures("<html>"), Err(FootyError::Parse(_))));
+    }
+
+    fn test_fixture(id: u64, home: u64, away: u64, timestamp: i64, status: &str) -> Fixture {
+        serde_json::from_value(serde_json::json!({
+            "fixture": {"id": id, "referee": "", "timezone": "UTC", "date": "2024-08-17T14:00:00+00:00", "timestamp": timestamp,
+                "periods": {"first": null, "second": null}, "venue": null,
+                "status": {"long": "", "short": status, "elapsed": null}},
+            "league": {"id": 39, "name": "Premier League", "country": "England", "logo": "", "flag": null, "season": 2024, "round": null},
+            "teams": {"home": {"id": home, "name": "Home", "logo": "", "winner": null}, "away": {"id": away, "name": "Away", "logo": "", "winner": null}},
+            "goals": {"home": 1, "away": 0}
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_format_score_shows_extra_time_penalties_and_half_time() {
+        let with_score = |status: &str, goals: (u64, u64), penalty: serde_json::Value| -> Fixture {
+            let mut fixture = serde_json::to_value(test_fixture(1, 40, 49, 100, status)).unwrap();
+            fixture["goals"] = serde_json::json!({"home": goals.0, "away": goals.1});
+            fixture["score"] = serde_json::json!({
+                "halftime": {"home": 1, "away": 0},
+                "fulltime": {"home": 1, "away": 1},
+                "extratime": {"home": 1, "away": 1},
+                "penalty": penalty,
+            });
+            serde_json::from_value(fixture).unwrap()
+        };
+
+        let plain = RenderContext::plain(Layout::Normal);
+        let mut half_time = RenderContext::plain(Layout::Normal);
+        half_time.half_time = true;
+
+        let penalties = with_score("PEN", (2, 2), serde_json::json!({"home": 3, "away": 4}));
+        assert_eq!(format_score(&plain, &penalties), "2 - 2 (4 - 3 pens)");
+        assert_eq!(format_score(&half_time, &penalties), "2 - 2 (4 - 3 pens) (HT 0 - 1)");
+
+        let extra_time = with_score("AET", (2, 3), serde_json::json!({"home": null, "away": null}));
+        assert_eq!(format_score(&plain, &extra_time), "3 - 2 AET");
+
+        assert_eq!(format_score(&half_time, &test_fixture(1, 40, 49, 100, "FT")), "0 - 1");
+
+        // a colour terminal bolds the goals whatever the global `colored` override says
+        let mut colour = RenderContext::plain(Layout::Normal);
+        colour.terminal = Terminal::new(ColorDepth::Ansi16, None);
+        assert_eq!(format_score(&colour, &extra_time), "\x1b[1m3 - 2\x1b[0m AET");
+    }
+
+    #[test]
+    fn test_scores_header_lines_up_with_the_rows() {
+        let fixtures = vec![vec![test_fixture(1, 40, 49, 100, "FT")], vec![]];
+
+        let compact = fixtures_table(&fixtures, CommandType::Scores, &RenderContext::plain(Layout::Compact));
+        assert_eq!(compact.render(), "Away Home\nAWA  HOM  0 - 1 on 08-17");
+
+        let normal = fixtures_table(&fixtures, CommandType::Scores, &RenderContext::plain(Layout::Normal));
+        assert_eq!(normal.render(), "Away Home\nAway Home 0 - 1 on 08-17");
+
+        assert!(fixtures_table(&[vec![]], CommandType::Scores, &RenderContext::plain(Layout::Normal)).is_empty());
+        assert!(!fixtures_table(&fixtures, CommandType::Live, &RenderContext::plain(Layout::Normal)).render().contains("Away Home\n"));
+    }
+
+    #[test]
+    fn test_rows_survive_missing_values_and_long_names() {
+        let mut fixture = serde_json::to_value(test_fixture(1, 40, 49, 100, "2H")).unwrap();
+        fixture["teams"]["away"]["name"] = serde_json::json!("Borussia Mönchengladbach Amateure II");
+        fixture["goals"] = serde_json::json!({"home": null, "away": null});
+        fixture["fixture"]["date"] = serde_json::json!("");
+        let fixture: Fixture = serde_json::from_value(fixture).unwrap();
+        let half_time = test_fixture(1, 40, 49, 100, "HT");
+
+        let mut normal = RenderContext::plain(Layout::Normal);
+        normal.half_time = true;
+        let mut table = Table::new();
+        table.row(format_live_row(&normal, &fixture));
+        table.row(format_live_row(&normal, &half_time));
+        table.row(format_score_row(&normal, &fixture));
+
+        assert_eq!(table.render(), [
+            "Borussia Mönchengladbach Amateure II Home : - | 2nd Half",
+            "Away                                 Home : 0 - 1 | HT",
+            "Borussia Mönchengladbach Amateure II Home - on - | 2nd Half",
+        ].join("\n"));
+
+        let compact = format_live_row(&RenderContext::plain(Layout::Compact), &fixture);
+        assert_eq!(compact[..2], ["BAM", "HOM"]);
+        let wide = format_schedule_row(&RenderContext::plain(Layout::Wide), &half_time);
+        assert_eq!(wide[3..], ["-", "-", "-"]);
+    }
+
+    #[test]
+    fn test_parse_fixtures_tolerates_nulls_and_skips_broken_fixtures() {
+        let mut sparse = serde_json::to_value(test_fixture(1, 40, 49, 100, "NS")).unwrap();
+        sparse["fixture"]["referee"] = serde_json::Value::Null;
+        sparse["fixture"]["venue"] = serde_json::json!({"id": null, "name": null, "city": null});
+        sparse["fixture"]["status"]["extra"] = serde_json::json!(null);
+        sparse["league"]["logo"] = serde_json::Value::Null;
+        sparse["score"] = serde_json::json!({"halftime": {"home": null, "away": null}});
+
+        let mut broken = serde_json::to_value(test_fixture(2, 33, 50, 100, "NS")).unwrap();
+        broken["teams"] = serde_json::Value::Null;
+
+        let json = serde_json::json!({"errors": [], "response": [sparse, broken]}).to_string();
+        let fixtures = parse_fixtures(&json).unwrap();
+
+        assert_eq!(fixtures.len(), 1);
+        assert_eq!(fixtures[0].fixture.id, 1);
+        assert!(fixtures[0].fixture.referee.is_none());
+    }
+
+    #[test]
+    fn test_collect_team_results_dedupes_shared_fixtures() {
+        let fixtures = vec![
+            test_fixture(1, 40, 49, 100, "FT"),
+            test_fixture(2, 40, 33, 200, "FT"),
+            test_fixture(3, 49, 40, 300, "FT"),
+            test_fixture(4, 40, 50, 400, "NS"),
+            test_fixture(5, 66, 49, 250, "PEN"),
+            // the same match again, as from a second team's fallback lookup
+            test_fixture(3, 49, 40, 300, "FT"),
+        ];
+
+        let results = collect_team_results(fixtures, &[40, 49, 529], 2);
+        let ids: Vec<Vec<u64>> = results.iter()
+            .map(|team_results| team_results.iter().map(|fixture| fixture.fixture.id).collect())
+            .collect();
+
+        // Liverpool (40) gets 3 and 2; Chelsea (49) already shares 3, so only 5 is new
+        assert_eq!(ids, vec![vec![3, 2], vec![5], vec![]]);
+    }
+
+    #[test]
+    fn test_short_of_results_counts_finished_fixtures_once() {
+        let fixtures = vec![
+            test_fixture(1, 40, 49, 100, "FT"),
+            test_fixture(2, 40, 33, 200, "FT"),
+            test_fixture(3, 49, 50, 300, "NS"),
+            test_fixture(1, 40, 49, 100, "FT"),
+        ];
+
+        // Chelsea (49) has one result and an unplayed game, Arsenal (42) none
+        assert_eq!(short_of_results(&fixtures, &[40, 49, 42], 2), vec![49, 42]);
+        assert_eq!(short_of_results(&fixtures, &[40, 49, 42], 1), vec![42]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_keeps_input_order() {
+        // later leagues answer first, results must still come back in league order
+        let leagues: Vec<u64> = vec![39, 140, 78, 135];
+        let results = fetch_all(leagues.clone(), |league_id| async move {
+            let delay = 40 - (league_id % 40);
+            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+            Ok(league_id)
+        }).await.unwrap();
+
+        assert_eq!(results, leagues);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_stops_on_error() {
+        let results: Result<Vec<u64>, FootyError> = fetch_all(vec![39, 140], |league_id| async move {
+            if league_id == 140 { Err(FootyError::Api(String::from("down"))) } else { Ok(league_id) }
+        }).await;
+
+        assert!(matches!(results, Err(FootyError::Api(_))));
+    }
+
     #[test]
     fn test_unix_to_cst() {
         let unix_time = 1700096621;
-        assert_eq!(unix_to_cst(unix_time), "19:03".to_string());
+        assert_eq!(unix_to_cst(unix_time, None), "19:03".to_string());
     }
 
     #[test]
     fn test_unix_to_date() {
         let unix_time = 1700096621;
-        assert_eq!(unix_to_date(unix_time), "11-15".to_string());
+        assert_eq!(unix_to_date(unix_time, None), "11-15".to_string());
+    }
+
+    #[test]
+    fn test_configured_timezone_overrides_local() {
+        let unix_time = 1700096621;
+        assert_eq!(unix_to_cst(unix_time, Some(chrono_tz::Asia::Tokyo)), "10:03".to_string());
+        assert_eq!(unix_to_date(unix_time, Some(chrono_tz::Asia::Tokyo)), "11-16".to_string());
     }
 
 }
@@ -0,0 +1,273 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use image::DynamicImage;
+
+use crate::{
+    build_client, cache::{self, CacheMode}, cli::ColorGenerateArgs, client::ApiFootballClient, error::FootyError, fetch_all,
+    render::RenderContext, team_colors::{self, ColorRecord, TeamColors}, teams_file::TeamsFile, warn_about_quota, Command,
+};
+
+// Logos are shrunk to this many pixels a side before clustering
+const SAMPLE_SIZE: u32 = 64;
+const CLUSTERS: usize = 5;
+const MAX_ITERATIONS: usize = 20;
+// Fixed so the same logo always gives the same colours
+const SEED: u64 = 0x666f6f7479;
+// A secondary colour has to look different from the primary one (RGB distance)...
+const MIN_SECONDARY_DISTANCE: f32 = 80.0;
+// ...and cover at least this share of the logo's coloured pixels
+const MIN_SECONDARY_SHARE: f32 = 0.1;
+
+struct Logo {
+    team_id: u64,
+    name: Option<String>,
+    path: PathBuf,
+}
+
+// Works out team colours from logo images and writes them into the colours file
+pub async fn generate(cmd: &Command, ctx: &RenderContext, args: &ColorGenerateArgs) -> Result<(), FootyError> {
+    let mut logos = vec![];
+    for path in args.logos.iter() {
+        match team_id_from_file_name(path) {
+            Some(team_id) => logos.push(Logo { team_id, name: None, path: path.clone() }),
+            None => eprintln!("Skipping {}: name logo files after the team ID, e.g. 40.png", path.display()),
+        }
+    }
+    if args.followed {
+        logos.extend(download_followed_logos(cmd).await?);
+    }
+
+    let mut records = vec![];
+    for logo in logos {
+        let colors = match image::open(&logo.path) {
+            Ok(image) => dominant_colors(&image),
+            Err(err) => {
+                eprintln!("Skipping {}: {}", logo.path.display(), err);
+                continue;
+            },
+        };
+        let Some(colors) = colors else {
+            eprintln!("Skipping {}: it's all white, black or transparent", logo.path.display());
+            continue;
+        };
+
+        println!(
+            "{} {} ({})",
+            ctx.terminal.paint_exact("██", colors.primary),
+            colors.secondary.map(|secondary| ctx.terminal.paint_exact("██", secondary)).unwrap_or_else(|| String::from("  ")),
+            logo.name.as_deref().map(|name| format!("{}, ID {}", name, logo.team_id)).unwrap_or_else(|| format!("ID {}", logo.team_id)),
+        );
+        records.push(ColorRecord { id: logo.team_id, name: logo.name, colors });
+    }
+
+    if records.is_empty() {
+        return Err(FootyError::Config(String::from("No team colours could be worked out from those logos")));
+    }
+
+    let output = args.to.as_deref().unwrap_or(&cmd.settings.colors.file);
+    let count = records.len();
+    team_colors::upsert(output, records)?;
+    println!("Saved colours for {} teams to {}", count, output.display());
+    Ok(())
+}
+
+// "40.png" -> 40, the same name api-sports gives its logo files
+fn team_id_from_file_name(path: &Path) -> Option<u64> {
+    path.file_stem()?.to_str()?.parse().ok().filter(|team_id| *team_id != 0)
+}
+
+// Logos are kept in the cache directory, so only new teams (or --refresh) hit the network.
+// A logo that can't be downloaded is reported and skipped, like a bad local file.
+async fn download_followed_logos(cmd: &Command) -> Result<Vec<Logo>, FootyError> {
+    let dir = cache::cache_dir()
+        .ok_or_else(|| FootyError::Config(String::from("Couldn't find a cache directory for logos. Set FOOTY_CACHE_DIR")))?
+        .join("logos");
+    fs::create_dir_all(&dir)?;
+
+    let teams = TeamsFile::new(&cmd.settings.teams_file).read_or_empty()?;
+    let is_cached = |team_id: u64| dir.join(format!("{}.png", team_id)).exists() && cmd.cache_mode != CacheMode::Refresh;
+    let (cached, missing): (Vec<_>, Vec<_>) = teams.into_iter().partition(|team| is_cached(team.id));
+
+    let mut logos: Vec<Logo> = cached.into_iter()
+        .map(|team| Logo { team_id: team.id, path: dir.join(format!("{}.png", team.id)), name: Some(team.name) })
+        .collect();
+    if missing.is_empty() { return Ok(logos); }
+    if cmd.cache_mode == CacheMode::Offline {
+        for team in missing { eprintln!("Skipping {}: its logo isn't cached and --offline is set", team.name); }
+        return Ok(logos);
+    }
+
+    // one team lookup per missing logo for its `logo` URL; the image itself doesn't count
+    let client = build_client(cmd)?;
+    warn_about_quota(cmd, missing.len());
+    let downloads = fetch_all(missing, |team| {
+        let (client, path) = (&client, dir.join(format!("{}.png", team.id)));
+        async move {
+            let downloaded = download_logo(client, team.id, &path).await;
+            Ok((team, path, downloaded))
+        }
+    }).await?;
+
+    for (team, path, downloaded) in downloads {
+        match downloaded {
+            Ok(()) => logos.push(Logo { team_id: team.id, name: Some(team.name), path }),
+            Err(err) => eprintln!("Skipping {}: {}", team.name, err),
+        }
+    }
+    Ok(logos)
+}
+
+// Uses the logo URL the API gives for the team
+async fn download_logo(client: &ApiFootballClient, team_id: u64, path: &Path) -> Result<(), FootyError> {
+    let url = client.team(team_id).await?
+        .into_iter()
+        .find_map(|info| info.team.logo.filter(|logo| !logo.is_empty()))
+        .ok_or_else(|| FootyError::Api(format!("the API has no logo for team {}", team_id)))?;
+
+    fs::write(path, client.download(&url).await?)?;
+    Ok(())
+}
+
+// The largest cluster of coloured pixels is the primary colour; the next largest that's both
+// distinct from it and big enough is the secondary one
+pub fn dominant_colors(image: &DynamicImage) -> Option<TeamColors> {
+    let pixels = sample_pixels(image);
+    if pixels.is_empty() { return None; }
+
+    let mut clusters = kmeans(&pixels, CLUSTERS);
+    clusters.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+    let primary = clusters[0].0;
+    let secondary = clusters.iter().skip(1)
+        .find(|(centroid, count)| {
+            distance(*centroid, primary).sqrt() >= MIN_SECONDARY_DISTANCE
+                && *count as f32 >= MIN_SECONDARY_SHARE * pixels.len() as f32
+        })
+        .map(|(centroid, _)| to_rgb(*centroid));
+
+    Some(TeamColors { primary: to_rgb(primary), secondary })
+}
+
+// Opaque pixels that aren't close to white or black, which nearly every logo has as a
+// background or outline
+fn sample_pixels(image: &DynamicImage) -> Vec<[f32; 3]> {
+    image.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).to_rgba8().pixels()
+        .filter(|pixel| pixel[3] >= 128)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .filter(|rgb| {
+            let (max, min) = (rgb.iter().max().copied().unwrap_or(0), rgb.iter().min().copied().unwrap_or(0));
+            max > 30 && min < 225
+        })
+        .map(|rgb| rgb.map(|channel| channel as f32))
+        .collect()
+}
+
+// k-means with k-means++ seeding; returns each centroid with how many pixels ended up in it
+fn kmeans(pixels: &[[f32; 3]], k: usize) -> Vec<([f32; 3], usize)> {
+    let mut rng = fastrand::Rng::with_seed(SEED);
+
+    // each new starting centroid is picked with probability proportional to its squared
+    // distance from the ones already chosen
+    let mut centroids = vec![pixels[rng.usize(..pixels.len())]];
+    while centroids.len() < k.min(pixels.len()) {
+        let weights: Vec<f32> = pixels.iter().map(|pixel| nearest(*pixel, &centroids).1).collect();
+        let total: f32 = weights.iter().sum();
+        // fewer distinct colours than clusters
+        if total == 0.0 { break; }
+
+        let mut target = rng.f32() * total;
+        let next = weights.iter().position(|weight| {
+            target -= weight;
+            target <= 0.0
+        }).unwrap_or(pixels.len() - 1);
+        centroids.push(pixels[next]);
+    }
+
+    let mut counts = vec![0; centroids.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut sums = vec![[0.0; 3]; centroids.len()];
+        counts = vec![0; centroids.len()];
+        for pixel in pixels {
+            let (index, _) = nearest(*pixel, &centroids);
+            for channel in 0..3 { sums[index][channel] += pixel[channel]; }
+            counts[index] += 1;
+        }
+
+        let mut moved = false;
+        for (index, centroid) in centroids.iter_mut().enumerate() {
+            if counts[index] == 0 { continue; }
+            let mean = sums[index].map(|sum| sum / counts[index] as f32);
+            moved |= mean != *centroid;
+            *centroid = mean;
+        }
+        if !moved { break; }
+    }
+
+    centroids.into_iter().zip(counts).collect()
+}
+
+// Index of the closest centroid and the squared distance to it
+fn nearest(pixel: [f32; 3], centroids: &[[f32; 3]]) -> (usize, f32) {
+    centroids.iter()
+        .map(|centroid| distance(pixel, *centroid))
+        .enumerate()
+        .fold((0, f32::MAX), |best, (index, distance)| if distance < best.1 { (index, distance) } else { best })
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|channel| (a[channel] - b[channel]).powi(2)).sum()
+}
+
+fn to_rgb(centroid: [f32; 3]) -> [u8; 3] {
+    centroid.map(|channel| channel.round().clamp(0.0, 255.0) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    // Bands of colour top to bottom, each `rows` pixels tall on a 100 pixel wide image
+    fn striped(bands: &[([u8; 4], u32)]) -> DynamicImage {
+        let height = bands.iter().map(|(_, rows)| rows).sum();
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(100, height, |_, y| {
+            let mut top = 0;
+            for (pixel, rows) in bands {
+                top += rows;
+                if y < top { return Rgba(*pixel); }
+            }
+            Rgba([0, 0, 0, 0])
+        }))
+    }
+
+    #[test]
+    fn test_dominant_colors_ignore_white_black_and_transparent() {
+        let logo = striped(&[
+            ([255, 255, 255, 255], 40),
+            ([200, 16, 46, 255], 30),
+            ([0, 0, 0, 0], 10),
+            ([10, 10, 10, 255], 10),
+            ([0, 178, 169, 255], 10),
+        ]);
+
+        let colors = dominant_colors(&logo).unwrap();
+        assert_eq!(colors.primary, [200, 16, 46]);
+        assert_eq!(colors.secondary, Some([0, 178, 169]));
+    }
+
+    #[test]
+    fn test_similar_or_tiny_clusters_are_not_secondary() {
+        let logo = striped(&[([200, 16, 46, 255], 60), ([210, 30, 50, 255], 38), ([0, 0, 255, 255], 2)]);
+        let colors = dominant_colors(&logo).unwrap();
+        assert!(colors.secondary.is_none(), "{:?}", colors);
+
+        assert!(dominant_colors(&striped(&[([255, 255, 255, 255], 50), ([0, 0, 0, 0], 50)])).is_none());
+    }
+
+    #[test]
+    fn test_team_id_from_file_name() {
+        assert_eq!(team_id_from_file_name(Path::new("logos/40.png")), Some(40));
+        assert_eq!(team_id_from_file_name(Path::new("liverpool.png")), None);
+    }
+}
@@ -3,23 +3,34 @@ use std::process;
 
 use log::Level;
 
-use footy::Command;
+use footy::{Command, FootyError};
 
 
 
 fn main() {
-    println!("\nGlobal Football CLI\n==================================================================");
+    let command = match Command::build(env::args()) {
+        Ok(command) => command,
+        // clap prints --help/--version or the usage error and picks the exit code
+        Err(FootyError::Usage(err)) => err.exit(),
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+    };
+
+    // JSON output has to be the only thing on stdout for scripts to parse it
+    if !command.prints_json() {
+        println!("\nGlobal Football CLI\n==================================================================");
+    }
 
     simple_logger::init_with_level(Level::Info).unwrap();
 
     let rt = tokio::runtime::Runtime::new().unwrap();
 
-    let command = Command::build(env::args()).unwrap_or_else(|err| {
-        eprintln!("Problem parsing arguments: {err}");
-        process::exit(1);
-    });
-
     let future = footy::run(command);
 
-    rt.block_on(future);
+    if let Err(err) = rt.block_on(future) {
+        eprintln!("{err}");
+        process::exit(1);
+    }
 }
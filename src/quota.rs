@@ -0,0 +1,170 @@
+use std::{env, fs, io, path::PathBuf};
+
+use chrono::{TimeZone, Utc};
+
+use reqwest::header::HeaderMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::atomic_file::write_atomic;
+
+const LIMIT_HEADER: &str = "x-ratelimit-requests-limit";
+const REMAINING_HEADER: &str = "x-ratelimit-requests-remaining";
+
+// How far back `footy quota` can show history
+const HISTORY_DAYS: i64 = 7;
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct QuotaState {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub updated_at: Option<i64>,
+    pub calls: Vec<QuotaCall>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct QuotaCall {
+    pub timestamp: i64,
+    pub endpoint: String,
+    pub remaining: Option<u64>,
+}
+
+impl QuotaState {
+    // The daily quota resets at midnight UTC, so yesterday's numbers say nothing about today
+    pub fn remaining_today(&self) -> Option<u64> {
+        let updated_at = self.updated_at?;
+        if same_utc_day(updated_at, Utc::now().timestamp()) { self.remaining } else { None }
+    }
+
+    pub fn calls_on(&self, day: i64) -> Vec<&QuotaCall> {
+        self.calls.iter().filter(|call| same_utc_day(call.timestamp, day)).collect()
+    }
+
+    fn record(&mut self, endpoint: &str, headers: &HeaderMap, now: i64) {
+        let limit = header_value(headers, LIMIT_HEADER);
+        let remaining = header_value(headers, REMAINING_HEADER);
+
+        if remaining.is_some() {
+            self.limit = limit.or(self.limit);
+            self.remaining = remaining;
+            self.updated_at = Some(now);
+        }
+
+        self.calls.push(QuotaCall {
+            timestamp: now,
+            endpoint: endpoint.to_string(),
+            remaining,
+        });
+        self.calls.retain(|call| now - call.timestamp < HISTORY_DAYS * 24 * 60 * 60);
+    }
+}
+
+// Persists the latest rate-limit headers plus a short log of calls made
+pub struct QuotaTracker {
+    path: PathBuf,
+}
+
+impl QuotaTracker {
+    pub fn new(path: PathBuf) -> QuotaTracker {
+        QuotaTracker { path }
+    }
+
+    // FOOTY_STATE_DIR wins, then $XDG_STATE_HOME/footy, falling back to the data dir
+    pub fn default_location() -> Option<QuotaTracker> {
+        let dir = match env::var("FOOTY_STATE_DIR") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => dirs::state_dir().or_else(dirs::data_dir)?.join("footy"),
+        };
+        Some(QuotaTracker::new(dir.join("quota.json")))
+    }
+
+    pub fn load(&self) -> QuotaState {
+        fs::read(&self.path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn record(&self, endpoint: &str, headers: &HeaderMap) -> io::Result<()> {
+        let mut state = self.load();
+        state.record(endpoint, headers, Utc::now().timestamp());
+
+        if let Some(dir) = self.path.parent() { fs::create_dir_all(dir)?; }
+        write_atomic(&self.path, &serde_json::to_vec_pretty(&state)?)
+    }
+}
+
+// Warns (without stopping the command) when it will probably run out of calls
+pub fn warn_if_insufficient(expected_calls: usize) {
+    let state = match QuotaTracker::default_location() {
+        Some(tracker) => tracker.load(),
+        None => return,
+    };
+
+    if let Some(warning) = insufficient_quota_warning(&state, expected_calls) {
+        eprintln!("{}", warning);
+    }
+}
+
+fn insufficient_quota_warning(state: &QuotaState, expected_calls: usize) -> Option<String> {
+    let remaining = state.remaining_today()?;
+    if (expected_calls as u64) <= remaining { return None; }
+
+    Some(format!(
+        "Warning: this command needs up to {} API calls but only {} of your daily {} remain",
+        expected_calls,
+        remaining,
+        state.limit.map(|limit| limit.to_string()).unwrap_or_else(|| String::from("?")),
+    ))
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+fn same_utc_day(a: i64, b: i64) -> bool {
+    let day = |timestamp| Utc.timestamp_opt(timestamp, 0).single().map(|date| date.date_naive());
+    day(a) == day(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn rate_limit_headers(limit: &'static str, remaining: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(LIMIT_HEADER, HeaderValue::from_static(limit));
+        headers.insert(REMAINING_HEADER, HeaderValue::from_static(remaining));
+        headers
+    }
+
+    #[test]
+    fn test_record_tracks_latest_headers_and_prunes_history() {
+        let now = Utc::now().timestamp();
+        let mut state = QuotaState::default();
+
+        state.record("/fixtures?live=39", &rate_limit_headers("100", "42"), now - 10 * 24 * 60 * 60);
+        state.record("/standings?league=39", &rate_limit_headers("100", "41"), now);
+        state.record("/teams?name=Arsenal", &HeaderMap::new(), now);
+
+        assert_eq!(state.limit, Some(100));
+        assert_eq!(state.remaining_today(), Some(41));
+        assert_eq!(state.calls.len(), 2);
+        assert_eq!(state.calls_on(now).len(), 2);
+    }
+
+    #[test]
+    fn test_warning_only_when_calls_exceed_todays_remaining() {
+        let mut state = QuotaState::default();
+        state.record("/fixtures?team=40", &rate_limit_headers("100", "3"), Utc::now().timestamp());
+
+        assert_eq!(insufficient_quota_warning(&state, 3), None);
+        assert!(insufficient_quota_warning(&state, 5).unwrap().contains("only 3 of your daily 100"));
+
+        // numbers from a previous day are stale after the reset
+        state.updated_at = Some(Utc::now().timestamp() - 2 * 24 * 60 * 60);
+        assert_eq!(insufficient_quota_warning(&state, 5), None);
+    }
+}
@@ -0,0 +1,55 @@
+use std::cell::OnceCell;
+
+use chrono_tz::Tz;
+
+use crate::{color::{self, Terminal}, config::ColorSettings, layout::Layout, team_colors::TeamPalette, Command};
+
+// Everything the formatters need, worked out once per run: team colours, what the terminal
+// can show, the layout and the timezone. Formatters only read from it, so building one by
+// hand is enough to test them without a terminal or a colours file.
+pub struct RenderContext {
+    pub terminal: Terminal,
+    // already resolved, never Auto
+    pub layout: Layout,
+    pub timezone: Option<Tz>,
+    pub half_time: bool,
+    // read from the colours file the first time a team is painted, so JSON output and
+    // commands that never show a team don't touch it
+    palette: OnceCell<TeamPalette>,
+    colors: ColorSettings,
+}
+
+impl RenderContext {
+    pub fn new(cmd: &Command) -> RenderContext {
+        let colors = &cmd.settings.colors;
+        RenderContext {
+            terminal: color::init(cmd.color, colors.enabled, colors.theme),
+            layout: cmd.layout.resolve(),
+            timezone: cmd.settings.timezone,
+            half_time: cmd.half_time,
+            palette: OnceCell::new(),
+            colors: colors.clone(),
+        }
+    }
+
+    // Writes `text` in the team's colour, or white if it has none
+    pub fn paint_team(&self, team_id: u64, text: &str) -> String {
+        if !self.terminal.colorize { return text.to_string(); }
+        self.palette.get_or_init(|| TeamPalette::load(&self.colors)).paint(&self.terminal, team_id, text)
+    }
+}
+
+// Plain output in the given layout, for tests
+#[cfg(test)]
+impl RenderContext {
+    pub fn plain(layout: Layout) -> RenderContext {
+        RenderContext {
+            terminal: Terminal::default(),
+            layout,
+            timezone: None,
+            half_time: false,
+            palette: OnceCell::from(TeamPalette::default()),
+            colors: crate::config::Settings::default().colors,
+        }
+    }
+}
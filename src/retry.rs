@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use reqwest::{header::{HeaderMap, RETRY_AFTER}, StatusCode};
+
+// Waits longer than this are treated as "come back tomorrow", not worth blocking on
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Exponential backoff with jitter: somewhere between half and all of base * 2^attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(2u32.saturating_pow(attempt));
+        let capped = exponential.min(self.max_delay);
+        let half = capped / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+
+    // How long to wait before the next attempt, or None when we should give up
+    pub fn delay_for(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_retries { return None; }
+
+        match retry_after {
+            Some(wait) if wait > MAX_RETRY_AFTER => None,
+            Some(wait) => Some(wait),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+}
+
+// Rate limiting and server-side hiccups are worth another try; anything else won't change
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// Timeouts, refused or reset connections and truncated bodies, but not malformed requests
+pub fn is_retryable_error(err: &reqwest::Error) -> bool {
+    !(err.is_builder() || err.is_redirect() || err.is_status() || err.is_decode())
+}
+
+// Retry-After is either a number of seconds or an HTTP date
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&Utc) - Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_with_jitter_and_is_capped() {
+        let policy = RetryPolicy::default();
+
+        for attempt in 0..6 {
+            let full = (policy.base_delay * 2u32.pow(attempt)).min(policy.max_delay);
+            let delay = policy.backoff(attempt);
+            assert!(delay >= full / 2 && delay <= full, "attempt {}: {:?}", attempt, delay);
+        }
+    }
+
+    #[test]
+    fn test_delay_for_honours_retry_after_and_limits() {
+        let policy = RetryPolicy { max_retries: 2, ..RetryPolicy::default() };
+
+        assert_eq!(policy.delay_for(0, Some(Duration::from_secs(3))), Some(Duration::from_secs(3)));
+        assert_eq!(policy.delay_for(0, Some(Duration::from_secs(3600))), None);
+        assert_eq!(policy.delay_for(2, None), None);
+        assert!(policy.delay_for(1, None).is_some());
+    }
+
+    #[test]
+    fn test_retry_after_header_formats() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,199 @@
+use serde::{Serialize, Deserialize};
+
+// Every short status code API-Football documents for a fixture.
+// Codes it adds later end up in Unknown rather than failing the whole response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "String", into = "String")]
+pub enum FixtureStatus {
+    TimeToBeDefined,
+    NotStarted,
+    FirstHalf,
+    HalfTime,
+    SecondHalf,
+    ExtraTime,
+    BreakTime,
+    PenaltiesInProgress,
+    Suspended,
+    Interrupted,
+    InProgress,
+    FullTime,
+    AfterExtraTime,
+    AfterPenalties,
+    Postponed,
+    Cancelled,
+    Abandoned,
+    TechnicalLoss,
+    WalkOver,
+    Unknown(String),
+}
+
+impl FixtureStatus {
+    pub fn from_code(code: &str) -> FixtureStatus {
+        match code.trim() {
+            "TBD" => FixtureStatus::TimeToBeDefined,
+            "NS" => FixtureStatus::NotStarted,
+            "1H" => FixtureStatus::FirstHalf,
+            "HT" => FixtureStatus::HalfTime,
+            "2H" => FixtureStatus::SecondHalf,
+            "ET" => FixtureStatus::ExtraTime,
+            "BT" => FixtureStatus::BreakTime,
+            "P" => FixtureStatus::PenaltiesInProgress,
+            "SUSP" => FixtureStatus::Suspended,
+            "INT" => FixtureStatus::Interrupted,
+            "LIVE" => FixtureStatus::InProgress,
+            "FT" => FixtureStatus::FullTime,
+            "AET" => FixtureStatus::AfterExtraTime,
+            "PEN" => FixtureStatus::AfterPenalties,
+            "PST" => FixtureStatus::Postponed,
+            "CANC" => FixtureStatus::Cancelled,
+            "ABD" => FixtureStatus::Abandoned,
+            "AWD" => FixtureStatus::TechnicalLoss,
+            "WO" => FixtureStatus::WalkOver,
+            other => FixtureStatus::Unknown(other.to_string()),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        match self {
+            FixtureStatus::TimeToBeDefined => "TBD",
+            FixtureStatus::NotStarted => "NS",
+            FixtureStatus::FirstHalf => "1H",
+            FixtureStatus::HalfTime => "HT",
+            FixtureStatus::SecondHalf => "2H",
+            FixtureStatus::ExtraTime => "ET",
+            FixtureStatus::BreakTime => "BT",
+            FixtureStatus::PenaltiesInProgress => "P",
+            FixtureStatus::Suspended => "SUSP",
+            FixtureStatus::Interrupted => "INT",
+            FixtureStatus::InProgress => "LIVE",
+            FixtureStatus::FullTime => "FT",
+            FixtureStatus::AfterExtraTime => "AET",
+            FixtureStatus::AfterPenalties => "PEN",
+            FixtureStatus::Postponed => "PST",
+            FixtureStatus::Cancelled => "CANC",
+            FixtureStatus::Abandoned => "ABD",
+            FixtureStatus::TechnicalLoss => "AWD",
+            FixtureStatus::WalkOver => "WO",
+            FixtureStatus::Unknown(code) => code,
+        }
+    }
+
+    // Short text for fixture rows
+    pub fn label(&self) -> &str {
+        match self {
+            FixtureStatus::TimeToBeDefined => "Time TBD",
+            FixtureStatus::NotStarted => "Not Started",
+            FixtureStatus::FirstHalf => "1st Half",
+            FixtureStatus::HalfTime => "Half Time",
+            FixtureStatus::SecondHalf => "2nd Half",
+            FixtureStatus::ExtraTime => "Extra Time",
+            FixtureStatus::BreakTime => "Break",
+            FixtureStatus::PenaltiesInProgress => "Penalties",
+            FixtureStatus::Suspended => "Suspended",
+            FixtureStatus::Interrupted => "Interrupted",
+            FixtureStatus::InProgress => "In Progress",
+            FixtureStatus::FullTime => "Full Time",
+            FixtureStatus::AfterExtraTime => "After Extra Time",
+            FixtureStatus::AfterPenalties => "After Penalties",
+            FixtureStatus::Postponed => "Postponed",
+            FixtureStatus::Cancelled => "Cancelled",
+            FixtureStatus::Abandoned => "Abandoned",
+            FixtureStatus::TechnicalLoss => "Awarded",
+            FixtureStatus::WalkOver => "Walkover",
+            FixtureStatus::Unknown(code) => code,
+        }
+    }
+
+    // The ball is (or is about to be) in play, breaks and stoppages included
+    pub fn is_live(&self) -> bool {
+        self.is_clock_running()
+            || matches!(
+                self,
+                FixtureStatus::HalfTime
+                    | FixtureStatus::BreakTime
+                    | FixtureStatus::PenaltiesInProgress
+                    | FixtureStatus::Suspended
+                    | FixtureStatus::Interrupted
+            )
+    }
+
+    // Live with the match clock ticking, so the elapsed minute means something
+    pub fn is_clock_running(&self) -> bool {
+        matches!(
+            self,
+            FixtureStatus::FirstHalf | FixtureStatus::SecondHalf | FixtureStatus::ExtraTime | FixtureStatus::InProgress
+        )
+    }
+
+    // Has a final result, including ones decided off the pitch
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            FixtureStatus::FullTime
+                | FixtureStatus::AfterExtraTime
+                | FixtureStatus::AfterPenalties
+                | FixtureStatus::TechnicalLoss
+                | FixtureStatus::WalkOver
+        )
+    }
+
+    pub fn is_not_started(&self) -> bool {
+        matches!(self, FixtureStatus::TimeToBeDefined | FixtureStatus::NotStarted)
+    }
+
+    // Won't go ahead as scheduled; postponed fixtures get a new date later
+    pub fn is_called_off(&self) -> bool {
+        matches!(self, FixtureStatus::Postponed | FixtureStatus::Cancelled | FixtureStatus::Abandoned)
+    }
+}
+
+impl From<String> for FixtureStatus {
+    fn from(code: String) -> Self {
+        FixtureStatus::from_code(&code)
+    }
+}
+
+impl From<FixtureStatus> for String {
+    fn from(status: FixtureStatus) -> Self {
+        status.code().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODES: [&str; 19] = [
+        "TBD", "NS", "1H", "HT", "2H", "ET", "BT", "P", "SUSP", "INT", "LIVE",
+        "FT", "AET", "PEN", "PST", "CANC", "ABD", "AWD", "WO",
+    ];
+
+    #[test]
+    fn test_every_documented_code_round_trips() {
+        for code in CODES {
+            let status = FixtureStatus::from_code(code);
+            assert!(!matches!(status, FixtureStatus::Unknown(_)), "{} isn't recognised", code);
+            assert_eq!(status.code(), code);
+        }
+
+        let status: FixtureStatus = serde_json::from_str(r#""XYZ""#).unwrap();
+        assert_eq!(status, FixtureStatus::Unknown(String::from("XYZ")));
+        assert_eq!(serde_json::to_string(&FixtureStatus::AfterPenalties).unwrap(), r#""PEN""#);
+    }
+
+    #[test]
+    fn test_each_code_has_exactly_one_category() {
+        for code in CODES {
+            let status = FixtureStatus::from_code(code);
+            let categories = [status.is_live(), status.is_finished(), status.is_not_started(), status.is_called_off()];
+            assert_eq!(categories.iter().filter(|category| **category).count(), 1, "{}", code);
+        }
+
+        assert!(FixtureStatus::HalfTime.is_live());
+        assert!(!FixtureStatus::HalfTime.is_clock_running());
+        assert!(FixtureStatus::ExtraTime.is_clock_running());
+        assert!(FixtureStatus::AfterExtraTime.is_finished());
+        assert!(FixtureStatus::Postponed.is_called_off());
+        assert!(!FixtureStatus::Unknown(String::from("XYZ")).is_live());
+    }
+}
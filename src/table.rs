@@ -0,0 +1,125 @@
+use unicode_width::UnicodeWidthStr;
+
+// Space between columns
+const GAP: usize = 1;
+
+// Rows of cells rendered as aligned columns. Widths are measured as they appear on screen:
+// colour codes take no room and accented or wide characters count as what they display as.
+#[derive(Default)]
+pub struct Table {
+    rows: Vec<Row>,
+}
+
+enum Row {
+    Cells(Vec<String>),
+    // printed as-is and left out of the column widths, e.g. a league name above its fixtures
+    Line(String),
+}
+
+impl Table {
+    pub fn new() -> Table {
+        Table::default()
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(Row::Cells(cells));
+    }
+
+    pub fn line(&mut self, text: impl Into<String>) {
+        self.rows.push(Row::Line(text.into()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    // The last cell of a row isn't padded, so rows don't end in trailing spaces
+    pub fn render(&self) -> String {
+        let widths = self.column_widths();
+
+        let lines: Vec<String> = self.rows.iter().map(|row| match row {
+            Row::Line(text) => text.clone(),
+            Row::Cells(cells) => {
+                let mut line = String::new();
+                for (index, cell) in cells.iter().enumerate() {
+                    line.push_str(cell);
+                    if index + 1 < cells.len() {
+                        line.push_str(&" ".repeat(widths[index] + GAP - display_width(cell)));
+                    }
+                }
+                line
+            },
+        }).collect();
+
+        lines.join("\n")
+    }
+
+    fn column_widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = vec![];
+        for row in self.rows.iter() {
+            let Row::Cells(cells) = row else { continue };
+            for (index, cell) in cells.iter().enumerate() {
+                match widths.get_mut(index) {
+                    Some(width) => *width = (*width).max(display_width(cell)),
+                    None => widths.push(display_width(cell)),
+                }
+            }
+        }
+        widths
+    }
+}
+
+// How many terminal columns `text` takes up
+pub fn display_width(text: &str) -> usize {
+    strip_ansi(text).width()
+}
+
+// Drops ANSI escape sequences such as the colour codes `colored` adds ("\x1b[1;38;2;200;16;46m")
+fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            plain.push(c);
+            continue;
+        }
+        // CSI sequences run until a final byte in '@'..='~'; other escapes are one character long
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) { break; }
+            }
+        }
+    }
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_width_ignores_colour_and_counts_characters() {
+        let colored_name = "\x1b[1;38;2;0;0;0mBeşiktaş\x1b[0m";
+
+        assert!(colored_name.len() > 8);
+        assert_eq!(display_width(colored_name), 8);
+        assert_eq!(display_width("Atlético Madrid"), 15);
+        assert_eq!(display_width("浦和レッズ"), 10);
+    }
+
+    #[test]
+    fn test_render_aligns_columns_and_skips_lines() {
+        let bayern = String::from("\x1b[31mBayern München\x1b[0m");
+
+        let mut table = Table::new();
+        table.line("Bundesliga");
+        table.row(vec![bayern.clone(), String::from("at Mainz"), String::from("14:30")]);
+        table.row(vec![String::from("Köln"), String::from("at Borussia Dortmund"), String::from("17:30")]);
+
+        assert_eq!(table.render(), format!(
+            "Bundesliga\n{} at Mainz             14:30\nKöln           at Borussia Dortmund 17:30",
+            bayern,
+        ));
+    }
+}
@@ -0,0 +1,341 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use csv::{ReaderBuilder, StringRecord, Trim, WriterBuilder};
+
+use crate::{atomic_file::write_atomic, color::{self, Terminal}, config::ColorSettings, error::FootyError};
+
+const BLACK: [u8; 3] = [0, 0, 0];
+const WHITE: [u8; 3] = [255, 255, 255];
+const HEADERS: [&str; 4] = ["id", "name", "primary", "secondary"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TeamColors {
+    pub primary: [u8; 3],
+    pub secondary: Option<[u8; 3]>,
+}
+
+// Juventus, Newcastle and co. On a dark background the name comes out white, on a light one black.
+const BLACK_AND_WHITE: TeamColors = TeamColors { primary: BLACK, secondary: Some(WHITE) };
+
+// One row of the colours file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorRecord {
+    pub id: u64,
+    pub name: Option<String>,
+    pub colors: TeamColors,
+}
+
+// Every team's colours, ready for painting names. Built once per run from the colours file
+// with the config's per-team colours on top.
+#[derive(Default)]
+pub struct TeamPalette {
+    colors: HashMap<u64, TeamColors>,
+    badges: bool,
+}
+
+impl TeamPalette {
+    // A missing colours file just means white names; a broken one is reported and skipped
+    pub fn load(settings: &ColorSettings) -> TeamPalette {
+        let mut colors = match read(&settings.file) {
+            Ok(colors) => colors,
+            Err(FootyError::Io(err)) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => {
+                eprintln!("Ignoring team colours: {}", err);
+                HashMap::new()
+            },
+        };
+        for (team_id, primary) in settings.overrides.iter() {
+            colors.insert(*team_id, TeamColors { primary: *primary, secondary: None });
+        }
+
+        TeamPalette { colors, badges: settings.badges }
+    }
+
+    #[cfg(test)]
+    pub fn new(colors: HashMap<u64, TeamColors>, badges: bool) -> TeamPalette {
+        TeamPalette { colors, badges }
+    }
+
+    // The name in whichever team colour reads best, after a badge of both colours if enabled
+    pub fn paint(&self, terminal: &Terminal, team_id: u64, text: &str) -> String {
+        let colors = self.colors.get(&team_id);
+        let name = match colors {
+            Some(colors) => terminal.paint(text, terminal.most_readable(colors.primary, colors.secondary)),
+            None => terminal.paint(text, WHITE),
+        };
+        if !self.badges || !terminal.colorize { return name; }
+
+        // teams without colours get a blank badge so names still line up
+        match colors {
+            Some(colors) => format!(
+                "{}{} {}",
+                terminal.paint_exact("█", colors.primary),
+                terminal.paint_exact("█", colors.secondary.unwrap_or(colors.primary)),
+                name,
+            ),
+            None => format!("   {}", name),
+        }
+    }
+}
+
+// Reads either format: `id,name,primary,secondary` with a header row, or the old headerless
+// id_rgb.csv (`id,"(r, g, b)"`, where teams without a colour have an empty second column)
+pub fn read(path: &Path) -> Result<HashMap<u64, TeamColors>, FootyError> {
+    Ok(read_records(path)?.0.into_iter().map(|record| (record.id, record.colors)).collect())
+}
+
+// Rows in file order, plus how many old-format rows were black and white. Every bad row is
+// reported with its line number, not just the first.
+fn read_records(path: &Path) -> Result<(Vec<ColorRecord>, usize), FootyError> {
+    let contents = fs::read_to_string(path)?;
+    let mut csv_reader = ReaderBuilder::new().has_headers(false).flexible(true).trim(Trim::All).from_reader(contents.as_bytes());
+
+    let mut rows = csv_reader.records().peekable();
+    let legacy = match rows.peek() {
+        Some(Ok(first)) => first.get(0) != Some("id"),
+        _ => true,
+    };
+    let columns = if legacy { None } else { Some(Columns::from_header(&rows.next().transpose()?.unwrap_or_default())?) };
+
+    let mut records = vec![];
+    let mut black_and_white = 0;
+    let mut problems = vec![];
+    for row in rows {
+        let row = row?;
+        let line = line_number(&contents, row.position().map_or(0, |position| position.byte() as usize));
+        if row.iter().all(|field| field.is_empty()) { continue; }
+
+        let parsed = match &columns {
+            Some(columns) => columns.parse(&row),
+            None => parse_legacy(&row),
+        };
+        match parsed {
+            Ok(record) => {
+                if columns.is_none() && record.colors == BLACK_AND_WHITE { black_and_white += 1; }
+                records.push(record);
+            },
+            Err(problem) => problems.push(format!("line {}: {}", line, problem)),
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(FootyError::Config(format!("{} has errors:\n  {}", path.display(), problems.join("\n  "))));
+    }
+    Ok((records, black_and_white))
+}
+
+// The csv reader's own line count ignores blank lines, and a row's byte offset points at any
+// blank lines before it, so count from the first character that isn't a line break
+fn line_number(contents: &str, byte: usize) -> usize {
+    let rest = contents.get(byte..).unwrap_or_default();
+    let start = byte + rest.len() - rest.trim_start_matches(['\r', '\n']).len();
+    contents[..start].matches('\n').count() + 1
+}
+
+// Converts an old id_rgb.csv into the new format; returns how many teams were written and
+// how many of them were black and white
+pub fn migrate(from: &Path, to: &Path) -> Result<(usize, usize), FootyError> {
+    if to.exists() {
+        return Err(FootyError::Config(format!("{} already exists. Move it out of the way or pick another --to", to.display())));
+    }
+
+    let (records, black_and_white) = read_records(from)?;
+    write(to, &records)?;
+
+    Ok((records.len(), black_and_white))
+}
+
+// Replaces the colours of teams already in the file and appends the rest, keeping a team's
+// name when the new record doesn't have one. A missing file is created.
+pub fn upsert(path: &Path, updates: Vec<ColorRecord>) -> Result<(), FootyError> {
+    let mut records = match read_records(path) {
+        Ok((records, _)) => records,
+        Err(FootyError::Io(err)) if err.kind() == io::ErrorKind::NotFound => vec![],
+        Err(err) => return Err(err),
+    };
+
+    for update in updates {
+        match records.iter_mut().find(|record| record.id == update.id) {
+            Some(record) => {
+                record.colors = update.colors;
+                if update.name.is_some() { record.name = update.name; }
+            },
+            None => records.push(update),
+        }
+    }
+    write(path, &records)
+}
+
+// Always the new format
+fn write(path: &Path, records: &[ColorRecord]) -> Result<(), FootyError> {
+    let mut csv_writer = WriterBuilder::new().from_writer(vec![]);
+    csv_writer.write_record(HEADERS)?;
+    for record in records {
+        csv_writer.write_record([
+            record.id.to_string(),
+            record.name.clone().unwrap_or_default(),
+            to_hex(record.colors.primary),
+            record.colors.secondary.map(to_hex).unwrap_or_default(),
+        ])?;
+    }
+
+    write_atomic(path, &csv_writer.into_inner().map_err(|err| err.into_error())?)?;
+    Ok(())
+}
+
+// Where each known column is in a file with a header row
+struct Columns {
+    id: usize,
+    name: Option<usize>,
+    primary: usize,
+    secondary: Option<usize>,
+}
+
+impl Columns {
+    fn from_header(header: &StringRecord) -> Result<Columns, FootyError> {
+        let find = |name: &str| header.iter().position(|column| column.eq_ignore_ascii_case(name));
+        let missing = |name: &str| FootyError::Config(format!("Colours file header needs a '{}' column (expected {})", name, HEADERS.join(",")));
+
+        Ok(Columns {
+            id: find("id").ok_or_else(|| missing("id"))?,
+            name: find("name"),
+            primary: find("primary").ok_or_else(|| missing("primary"))?,
+            secondary: find("secondary"),
+        })
+    }
+
+    fn parse(&self, row: &StringRecord) -> Result<ColorRecord, String> {
+        let team_id = parse_id(row.get(self.id).unwrap_or_default())?;
+        let primary = parse_color(row.get(self.primary).unwrap_or_default(), "primary")?;
+        let secondary = match self.secondary.and_then(|index| row.get(index)).filter(|value| !value.is_empty()) {
+            Some(value) => Some(parse_color(value, "secondary")?),
+            None => None,
+        };
+        let name = self.name.and_then(|index| row.get(index)).filter(|name| !name.is_empty()).map(String::from);
+        Ok(ColorRecord { id: team_id, name, colors: TeamColors { primary, secondary } })
+    }
+}
+
+// The old file left the colour empty for teams whose logos are only black and white
+fn parse_legacy(row: &StringRecord) -> Result<ColorRecord, String> {
+    let team_id = parse_id(row.get(0).unwrap_or_default())?;
+    let colors = match row.get(1).unwrap_or_default() {
+        "" => BLACK_AND_WHITE,
+        value => TeamColors { primary: parse_color(value, "colour")?, secondary: None },
+    };
+    Ok(ColorRecord { id: team_id, name: None, colors })
+}
+
+fn parse_id(value: &str) -> Result<u64, String> {
+    value.parse::<u64>().ok().filter(|id| *id != 0).ok_or_else(|| format!("'{}' isn't a team ID", value))
+}
+
+fn parse_color(value: &str, column: &str) -> Result<[u8; 3], String> {
+    if value.is_empty() { return Err(format!("missing {} colour", column)); }
+    color::parse_rgb(value).ok_or_else(|| format!("{} colour '{}' should look like #c8102e or (200, 16, 46)", column, value))
+}
+
+fn to_hex(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf};
+
+    use crate::color::ColorDepth;
+
+    use super::*;
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("footy-colors-{}-{}.csv", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_reads_both_formats() {
+        let new = temp_file("new", "id,name,primary,secondary\n40,Liverpool,#C8102E,#00b2a9\n50,Manchester City,\"(108, 171, 221)\",\n");
+        let legacy = temp_file("legacy", "50,\"(0, 35, 89)\"\n160,\n");
+        let (new_colors, legacy_colors) = (read(&new).unwrap(), read(&legacy).unwrap());
+        fs::remove_file(new).unwrap();
+        fs::remove_file(legacy).unwrap();
+
+        assert_eq!(new_colors[&40], TeamColors { primary: [200, 16, 46], secondary: Some([0, 178, 169]) });
+        assert_eq!(new_colors[&50], TeamColors { primary: [108, 171, 221], secondary: None });
+        assert_eq!(legacy_colors.len(), 2);
+        assert_eq!(legacy_colors[&50].primary, [0, 35, 89]);
+        assert_eq!(legacy_colors[&160], BLACK_AND_WHITE);
+    }
+
+    // The id_rgb.csv that shipped before team_colors.csv, kept so old setups keep loading
+    #[test]
+    fn test_read_rgb_csv() {
+        let colors = read(Path::new("./id_rgb.csv")).unwrap();
+
+        assert_eq!(colors[&50], TeamColors { primary: [0, 35, 89], secondary: None });
+        assert_eq!(colors[&496], BLACK_AND_WHITE);
+        assert_eq!(colors.len(), 148);
+    }
+
+    #[test]
+    fn test_reports_every_bad_line() {
+        let path = temp_file("bad", "id,primary\n40,#c8102e\nabc,#ffffff\n50,navy\n\n42,\n");
+        let err = read(&path).unwrap_err().to_string();
+        fs::remove_file(path).unwrap();
+
+        assert!(err.contains("line 3: 'abc' isn't a team ID"), "{}", err);
+        assert!(err.contains("line 4: primary colour 'navy'"), "{}", err);
+        assert!(err.contains("line 6: missing primary colour"), "{}", err);
+        assert!(!err.contains("line 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_migrate_legacy_file() {
+        let from = temp_file("migrate-from", "50,\"(0, 35, 89)\"\n160,\n42,\"(210, 37, 77)\"\n");
+        let to = from.with_extension("new.csv");
+        let _ = fs::remove_file(&to);
+
+        let counts = migrate(&from, &to).unwrap();
+        let contents = fs::read_to_string(&to).unwrap();
+        let again = migrate(&from, &to);
+        fs::remove_file(from).unwrap();
+        fs::remove_file(to).unwrap();
+
+        assert_eq!(counts, (3, 1));
+        assert_eq!(contents, "id,name,primary,secondary\n50,,#002359,\n160,,#000000,#ffffff\n42,,#d2254d,\n");
+        assert!(again.is_err());
+    }
+
+    #[test]
+    fn test_upsert_keeps_names_and_order() {
+        let path = temp_file("upsert", "id,name,primary,secondary\n40,Liverpool,#c8102e,\n50,,#002359,\n");
+        let record = |id: u64, name: Option<&str>, primary: [u8; 3]| ColorRecord {
+            id,
+            name: name.map(String::from),
+            colors: TeamColors { primary, secondary: Some([255, 255, 255]) },
+        };
+
+        upsert(&path, vec![record(40, None, [0, 178, 169]), record(42, Some("Arsenal"), [239, 1, 7])]).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(contents, "id,name,primary,secondary\n40,Liverpool,#00b2a9,#ffffff\n50,,#002359,\n42,Arsenal,#ef0107,#ffffff\n");
+    }
+
+    #[test]
+    fn test_badges_only_with_colour() {
+        let colors = HashMap::from([(40, TeamColors { primary: [200, 16, 46], secondary: None })]);
+        let palette = TeamPalette::new(colors, true);
+
+        assert_eq!(palette.paint(&Terminal::default(), 40, "Liverpool"), "Liverpool");
+
+        let terminal = Terminal::new(ColorDepth::Ansi256, None);
+        assert_eq!(
+            palette.paint(&terminal, 40, "Liverpool"),
+            "\x1b[38;5;160m█\x1b[0m\x1b[38;5;160m█\x1b[0m \x1b[38;5;160mLiverpool\x1b[0m",
+        );
+        // teams without colours still line up
+        assert_eq!(palette.paint(&terminal, 50, "City"), "   \x1b[38;5;231mCity\x1b[0m");
+    }
+}
@@ -0,0 +1,175 @@
+use std::{fs::{self, OpenOptions}, io, path::{Path, PathBuf}};
+
+use csv::{ReaderBuilder, Trim, WriterBuilder};
+
+use fs2::FileExt;
+
+use crate::{atomic_file::{sibling, write_atomic}, error::FootyError, TeamCSVRecord};
+
+// teams.csv: one `name,id` row per followed team, no header.
+// Every reader and writer goes through here so they all agree on the path.
+pub struct TeamsFile {
+    path: PathBuf,
+}
+
+impl TeamsFile {
+    pub fn new(path: &Path) -> TeamsFile {
+        TeamsFile { path: path.to_path_buf() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Rows in file order; blank lines, stray spaces and a missing trailing newline are all fine
+    pub fn read(&self) -> Result<Vec<TeamCSVRecord>, FootyError> {
+        let mut csv_reader = ReaderBuilder::new().has_headers(false).trim(Trim::All).from_path(&self.path)?;
+
+        csv_reader.deserialize()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| FootyError::Config(format!("{} isn't a valid teams file: {}", self.path.display(), err)))
+    }
+
+    // no file yet just means no teams followed yet
+    pub fn read_or_empty(&self) -> Result<Vec<TeamCSVRecord>, FootyError> {
+        match self.read() {
+            Err(FootyError::Io(err)) if err.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+            result => result,
+        }
+    }
+
+    // Appends teams that aren't followed yet (by ID) and returns the ones that were added
+    pub fn add(&self, teams: Vec<TeamCSVRecord>) -> Result<Vec<TeamCSVRecord>, FootyError> {
+        self.update(|records| {
+            let mut added = vec![];
+            for team in teams {
+                if records.iter().any(|record| record.id == team.id) { continue; }
+                records.push(team.clone());
+                added.push(team);
+            }
+            added
+        })
+    }
+
+    // Matches by name (case-insensitive) or team ID and returns the removed rows
+    pub fn remove(&self, team: &str) -> Result<Vec<TeamCSVRecord>, FootyError> {
+        let team = team.trim();
+        self.update(|records| {
+            let (removed, kept) = records.drain(..)
+                .partition(|record| record.name.eq_ignore_ascii_case(team) || record.id.to_string() == team);
+            *records = kept;
+            removed
+        })
+    }
+
+    // Read-modify-write while holding an exclusive lock, so two footy runs can't lose each
+    // other's changes. The lock lives in a side file because the rename replaces teams.csv.
+    fn update<T>(&self, change: impl FnOnce(&mut Vec<TeamCSVRecord>) -> T) -> Result<T, FootyError> {
+        if let Some(dir) = self.path.parent() { fs::create_dir_all(dir)?; }
+
+        let lock = OpenOptions::new().create(true).truncate(false).write(true).open(sibling(&self.path, "lock"))?;
+        lock.lock_exclusive()?;
+
+        let mut records = self.read_or_empty()?;
+        let result = change(&mut records);
+        self.write(&records)?;
+
+        lock.unlock()?;
+        Ok(result)
+    }
+
+    // Never leaves a half-written teams.csv, even on a crash
+    fn write(&self, records: &[TeamCSVRecord]) -> Result<(), FootyError> {
+        let mut csv_writer = WriterBuilder::new().has_headers(false).from_writer(vec![]);
+        for record in records {
+            csv_writer.serialize(record)?;
+        }
+
+        write_atomic(&self.path, &csv_writer.into_inner().map_err(|err| err.into_error())?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, sync::Arc, thread};
+
+    use super::*;
+
+    fn temp_teams_file(name: &str, contents: Option<&str>) -> TeamsFile {
+        let path = env::temp_dir().join(format!("footy-teams-{}-{}.csv", name, std::process::id()));
+        match contents {
+            Some(contents) => fs::write(&path, contents).unwrap(),
+            None => { let _ = fs::remove_file(&path); },
+        }
+        TeamsFile::new(&path)
+    }
+
+    fn cleanup(teams_file: &TeamsFile) {
+        fs::remove_file(teams_file.path()).unwrap();
+        let _ = fs::remove_file(sibling(teams_file.path(), "lock"));
+    }
+
+    fn team(name: &str, id: u64) -> TeamCSVRecord {
+        TeamCSVRecord { name: name.to_string(), id }
+    }
+
+    fn names(teams: &[TeamCSVRecord]) -> Vec<&str> {
+        teams.iter().map(|team| team.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_add_after_missing_trailing_newline() {
+        let teams_file = temp_teams_file("newline", Some("Liverpool,40\n\nAC Milan, 489"));
+
+        let added = teams_file.add(vec![team("Arsenal", 42), team("Liverpool", 40)]).unwrap();
+        let contents = fs::read_to_string(teams_file.path()).unwrap();
+        cleanup(&teams_file);
+
+        assert_eq!(names(&added), vec!["Arsenal"]);
+        assert_eq!(contents, "Liverpool,40\nAC Milan,489\nArsenal,42\n");
+    }
+
+    #[test]
+    fn test_remove_by_name_or_id() {
+        let teams_file = temp_teams_file("remove", Some("Liverpool,40\nAC Milan,489\nArsenal,42\n"));
+
+        assert_eq!(names(&teams_file.remove("ac milan").unwrap()), vec!["AC Milan"]);
+        assert_eq!(names(&teams_file.remove("42").unwrap()), vec!["Arsenal"]);
+        assert!(teams_file.remove("Chelsea").unwrap().is_empty());
+        let remaining = teams_file.read().unwrap();
+        cleanup(&teams_file);
+
+        assert_eq!(names(&remaining), vec!["Liverpool"]);
+    }
+
+    #[test]
+    fn test_missing_file_is_empty_until_first_add() {
+        let teams_file = temp_teams_file("missing", None);
+
+        assert!(matches!(teams_file.read(), Err(FootyError::Io(_))));
+        assert!(teams_file.read_or_empty().unwrap().is_empty());
+
+        teams_file.add(vec![team("Liverpool", 40)]).unwrap();
+        let teams = teams_file.read().unwrap();
+        cleanup(&teams_file);
+
+        assert_eq!(names(&teams), vec!["Liverpool"]);
+    }
+
+    #[test]
+    fn test_concurrent_adds_are_not_lost() {
+        let teams_file = Arc::new(temp_teams_file("concurrent", None));
+
+        let handles: Vec<_> = (1..=8).map(|id| {
+            let teams_file = Arc::clone(&teams_file);
+            thread::spawn(move || teams_file.add(vec![team(&format!("Team {}", id), id)]).unwrap())
+        }).collect();
+        for handle in handles { handle.join().unwrap(); }
+
+        let teams = teams_file.read().unwrap();
+        cleanup(&teams_file);
+
+        assert_eq!(teams.len(), 8);
+    }
+}
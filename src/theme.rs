@@ -0,0 +1,174 @@
+use std::env;
+
+use clap::ValueEnum;
+
+// WCAG AA for normal text
+pub const MIN_CONTRAST: f64 = 4.5;
+
+const BLACK: [u8; 3] = [0, 0, 0];
+const WHITE: [u8; 3] = [255, 255, 255];
+
+// The terminal background team colours have to stay readable on
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Default)]
+pub enum Theme {
+    #[default]
+    Auto,
+    Dark,
+    Light,
+}
+
+impl Theme {
+    // Auto asks the terminal (OSC 11), then checks COLORFGBG, and assumes dark if neither answers
+    pub fn background(self) -> [u8; 3] {
+        match self {
+            Theme::Dark => BLACK,
+            Theme::Light => WHITE,
+            Theme::Auto => query_background()
+                .or_else(|| env::var("COLORFGBG").ok().and_then(|value| colorfgbg_background(&value)))
+                .unwrap_or(BLACK),
+        }
+    }
+}
+
+// Lightens (on dark backgrounds) or darkens (on light ones) just enough to reach `min_ratio`,
+// keeping as much of the team's colour as possible
+pub fn ensure_contrast(rgb: [u8; 3], background: [u8; 3], min_ratio: f64) -> [u8; 3] {
+    if contrast_ratio(rgb, background) >= min_ratio { return rgb; }
+
+    let target = if contrast_ratio(WHITE, background) >= contrast_ratio(BLACK, background) { WHITE } else { BLACK };
+    if contrast_ratio(target, background) < min_ratio { return target; }
+
+    // binary search for the smallest mix towards the target that's readable
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..16 {
+        let middle = (low + high) / 2.0;
+        if contrast_ratio(mix(rgb, target, middle), background) >= min_ratio { high = middle; } else { low = middle; }
+    }
+    mix(rgb, target, high)
+}
+
+// https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio
+pub fn contrast_ratio(a: [u8; 3], b: [u8; 3]) -> f64 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+fn relative_luminance(rgb: [u8; 3]) -> f64 {
+    let [r, g, b] = rgb.map(|channel| {
+        let channel = channel as f64 / 255.0;
+        if channel <= 0.03928 { channel / 12.92 } else { ((channel + 0.055) / 1.055).powf(2.4) }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+fn mix(from: [u8; 3], to: [u8; 3], amount: f64) -> [u8; 3] {
+    let mut mixed = [0; 3];
+    for channel in 0..3 {
+        mixed[channel] = (from[channel] as f64 + (to[channel] as f64 - from[channel] as f64) * amount).round() as u8;
+    }
+    mixed
+}
+
+// "15;0" (foreground;background, as set by rxvt and Konsole); 7 and 9-15 are the light colours
+fn colorfgbg_background(value: &str) -> Option<[u8; 3]> {
+    match value.rsplit(';').next()?.trim().parse::<u8>().ok()? {
+        7 | 9..=15 => Some(WHITE),
+        _ => Some(BLACK),
+    }
+}
+
+// Reply to OSC 11 looks like "\x1b]11;rgb:1e1e/1e1e/2e2e\x07", with 1 to 4 hex digits per channel
+fn parse_osc11(reply: &str) -> Option<[u8; 3]> {
+    let rgb = reply.split("rgb:").nth(1)?;
+    let rgb = rgb.trim_end_matches(['\x07', '\\', '\x1b']);
+
+    let channels: Vec<u8> = rgb.split('/').map(|hex| {
+        let value = u32::from_str_radix(hex.get(..hex.len().min(4))?, 16).ok()?;
+        let max = 16u32.pow(hex.len().min(4) as u32) - 1;
+        Some((value * 255 / max) as u8)
+    }).collect::<Option<_>>()?;
+
+    channels.try_into().ok()
+}
+
+// Asks the terminal for its background colour. Terminals that don't support the query
+// just don't answer, so reads give up after a fifth of a second. Background jobs don't ask.
+#[cfg(unix)]
+fn query_background() -> Option<[u8; 3]> {
+    use std::{fs::OpenOptions, io::{Read, Write}, os::unix::io::AsRawFd};
+
+    let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty").ok()?;
+    let fd = tty.as_raw_fd();
+
+    // A background job (`footy live &`) would be stopped with SIGTTOU by tcsetattr, so only
+    // the foreground process group asks
+    // SAFETY: both only read process state
+    if unsafe { libc::tcgetpgrp(fd) != libc::getpgrp() } { return None; }
+
+    // SAFETY: termios is plain data, and tcgetattr fills it in or fails on a non-terminal fd
+    let mut original: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut original) } != 0 { return None; }
+
+    // no echo, no line buffering, reads time out after VTIME tenths of a second
+    let mut raw = original;
+    raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+    raw.c_cc[libc::VMIN] = 0;
+    raw.c_cc[libc::VTIME] = 2;
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 { return None; }
+
+    let mut reply = Vec::new();
+    if tty.write_all(b"\x1b]11;?\x07").and_then(|_| tty.flush()).is_ok() {
+        let mut byte = [0; 1];
+        // ends with BEL or ST (ESC \)
+        while reply.len() < 64 && matches!(tty.read(&mut byte), Ok(1)) {
+            reply.push(byte[0]);
+            if byte[0] == b'\x07' || reply.ends_with(b"\x1b\\") { break; }
+        }
+    }
+
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    parse_osc11(&String::from_utf8_lossy(&reply))
+}
+
+#[cfg(not(unix))]
+fn query_background() -> Option<[u8; 3]> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contrast_ratio() {
+        assert!((contrast_ratio(BLACK, WHITE) - 21.0).abs() < 0.01);
+        assert!((contrast_ratio([119, 119, 119], WHITE) - 4.48).abs() < 0.01);
+        assert_eq!(contrast_ratio(WHITE, WHITE), 1.0);
+    }
+
+    #[test]
+    fn test_ensure_contrast_lightens_or_darkens_only_as_needed() {
+        // Newcastle's navy on a dark terminal
+        let navy = ensure_contrast([0, 35, 89], BLACK, MIN_CONTRAST);
+        assert!(contrast_ratio(navy, BLACK) >= MIN_CONTRAST);
+        assert!(navy[2] > navy[0], "should still be blue, got {:?}", navy);
+
+        // the white fallback on a light terminal
+        let white = ensure_contrast(WHITE, WHITE, MIN_CONTRAST);
+        assert!(contrast_ratio(white, WHITE) >= MIN_CONTRAST);
+        assert!(white[0] > 0, "should darken only as far as needed, got {:?}", white);
+
+        assert_eq!(ensure_contrast([200, 16, 46], WHITE, MIN_CONTRAST), [200, 16, 46]);
+    }
+
+    #[test]
+    fn test_parse_terminal_background() {
+        assert_eq!(parse_osc11("\x1b]11;rgb:1e1e/1e1e/2e2e\x07"), Some([30, 30, 46]));
+        assert_eq!(parse_osc11("\x1b]11;rgb:ff/ff/ff\x1b\\"), Some(WHITE));
+        assert_eq!(parse_osc11("garbage"), None);
+
+        assert_eq!(colorfgbg_background("15;0"), Some(BLACK));
+        assert_eq!(colorfgbg_background("0;default;15"), Some(WHITE));
+        assert_eq!(colorfgbg_background("default"), None);
+    }
+}
@@ -0,0 +1,100 @@
+use std::{env, fs, io::{Read, Write}, net::TcpListener, path::{Path, PathBuf}, process::{Command, Output, Stdio}, thread};
+
+use serde_json::Value;
+
+// A config and teams file of its own, so the developer's real ones are never read
+fn fixture_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("footy-cli-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("teams.csv"), "Liverpool,40\nArsenal,42\n").unwrap();
+    fs::write(dir.join("config.toml"), format!("teams_file = \"{}\"\n", dir.join("teams.csv").display())).unwrap();
+    dir
+}
+
+fn footy(dir: &Path, args: &[&str]) -> Output {
+    footy_command(dir, args).output().unwrap()
+}
+
+fn footy_command(dir: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_footy"));
+    command.arg("--config")
+        .arg(dir.join("config.toml"))
+        .args(args)
+        .env_remove("CONFIG_PATH")
+        .env_remove("RGB_PATH")
+        .env("FOOTY_CACHE_DIR", dir.join("cache"))
+        .env("FOOTY_STATE_DIR", dir.join("state"));
+    command
+}
+
+// Answers every request with `body` until the test ends; returns the base URL
+fn mock_api(body: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut buf = [0u8; 8192];
+            let _ = stream.read(&mut buf).unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body,
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    format!("http://{}", addr)
+}
+
+#[test]
+fn test_teams_list_json_is_all_that_goes_to_stdout() {
+    let dir = fixture_dir("teams-json");
+
+    for args in [&["teams", "list", "--json"][..], &["--format", "json", "teams", "list"][..]] {
+        let output = footy(&dir, args);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+        let teams: Value = serde_json::from_slice(&output.stdout)
+            .unwrap_or_else(|err| panic!("{:?} didn't print plain JSON ({}): {}", args, err, String::from_utf8_lossy(&output.stdout)));
+        assert_eq!(teams[0]["name"], "Liverpool");
+        assert_eq!(teams[1]["id"], 42);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_teams_add_fails_on_an_ambiguous_name_without_a_terminal() {
+    let dir = fixture_dir("teams-add");
+    let base_url = mock_api(r#"{"errors": [], "response": [
+        {"team": {"id": 42, "name": "Arsenal", "code": "ARS", "country": "England", "founded": 1886, "national": false, "logo": null},
+         "venue": {"id": 494, "name": "Emirates Stadium", "city": "London"}},
+        {"team": {"id": 1080, "name": "Arsenal Tula", "code": null, "country": "Russia", "founded": 1946, "national": false, "logo": null},
+         "venue": {"id": null, "name": null, "city": null}}
+    ]}"#);
+
+    let output = footy_command(&dir, &["teams", "add", "Arsenal"])
+        .env("FOOTY_API_KEY", "test-key")
+        .env("FOOTY_API_BASE_URL", &base_url)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("2. Arsenal Tula") && stderr.contains("--id"), "{}", stderr);
+    assert_eq!(fs::read_to_string(dir.join("teams.csv")).unwrap(), "Liverpool,40\nArsenal,42\n");
+
+    // narrowed down to one, it's added without asking
+    let output = footy_command(&dir, &["teams", "add", "Arsenal", "--country", "Russia"])
+        .env("FOOTY_API_KEY", "test-key")
+        .env("FOOTY_API_BASE_URL", &base_url)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(fs::read_to_string(dir.join("teams.csv")).unwrap().contains("1080"));
+
+    fs::remove_dir_all(dir).unwrap();
+}